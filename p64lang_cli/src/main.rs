@@ -1,12 +1,28 @@
 extern crate p64lang;
 
+use std::env;
 use std::io::{self, Read};
+use std::process;
 
-use p64lang::interpret;
-use p64lang::interpreter::Scope;
+use p64lang::ast::ExecResult;
+use p64lang::interpret_with_config;
+use p64lang::interpreter::{Arithmetic, Config, Scope};
 use p64lang::runtime::insert_native_functions;
 
 fn main() {
+    let mut config = Config::default();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--strict"     => config.strict = true,
+            "--saturating" => config.arithmetic = Arithmetic::Saturating,
+            "--wrapping"   => config.arithmetic = Arithmetic::Wrapping,
+            _ => {
+                eprintln!("Usage: p64lang_cli [--strict] [--saturating | --wrapping] < SCRIPT");
+                process::exit(2);
+            },
+        }
+    }
+
     let mut buffer = String::new();
     io::stdin()
         .read_to_string(&mut buffer)
@@ -14,6 +30,16 @@ fn main() {
 
     let mut scope = Scope::new();
     insert_native_functions(&mut scope);
-    let res = interpret(&buffer, scope);
-    println!("Result: {:?}", res.exec_result);
+    let res = interpret_with_config(&buffer, scope, config);
+    match res.exec_result {
+        ExecResult::ParseError(ref e) => {
+            eprintln!("Parse error: {}", e);
+            process::exit(1);
+        },
+        ExecResult::Error(ref e) => {
+            eprintln!("Runtime error: {}", e);
+            process::exit(1);
+        },
+        ref x => println!("Result: {:?}", x),
+    };
 }
@@ -13,7 +13,7 @@ use alloc::string::String;
 
 use wasm_bindgen::prelude::*;
 
-use p64lang::ast::{NativeFunction, Value};
+use p64lang::ast::{ExecResult, NativeFunction, Value};
 use p64lang::interpreter::{Scope, ScopeChain};
 use p64lang::interpret;
 
@@ -70,5 +70,9 @@ pub fn interpret_str(src: &str) -> String {
     scope.native_funcs.insert("print",   Rc::new(NFPrint   {}));
     scope.native_funcs.insert("println", Rc::new(NFPrintLn {}));
     let res = interpret(src, scope);
-    format!("Result: {:?}", res.exec_result)
+    match res.exec_result {
+        ExecResult::ParseError(e) => format!("Parse error: {}", e),
+        ExecResult::Error(e)      => format!("Runtime error: {}", e),
+        x => format!("Result: {:?}", x),
+    }
 }
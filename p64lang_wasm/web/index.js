@@ -51,7 +51,7 @@ js.then((js) => {
 let counter = 0;\n\
 loop {\n\
     println("fib(", counter, ") = ", fib(counter));\n\
-    let counter = counter + 1;\n\
+    counter = counter + 1;\n\
     if counter > 20 { break; };\n\
 }';
   });
@@ -64,9 +64,9 @@ loop {\n\
   let i = n;\n\
   loop {\n\
     let temp = res;\n\
-    let res = res + prev;\n\
-    let prev = temp;\n\
-    let i = i - 1;\n\
+    res = res + prev;\n\
+    prev = temp;\n\
+    i = i - 1;\n\
     if i == 0 { break; };\n\
   };\n\
   return res;\n\
@@ -74,7 +74,7 @@ loop {\n\
 let counter = 0;\n\
 loop {\n\
   println("fib(", counter, ") = ", fib(counter));\n\
-  let counter = counter + 1;\n\
+  counter = counter + 1;\n\
   if counter > 28 { break; };\n\
 }';
   });
@@ -87,18 +87,18 @@ fn fib(n) {\n\
   let b = fib_cache[n - 2];\n\
   if !a {\n\
     fib_cache[n - 1] = fib(n - 1);\n\
-    let a = fib_cache[n - 1];\n\
+    a = fib_cache[n - 1];\n\
   };\n\
   if !b {\n\
     fib_cache[n - 2] = fib(n - 2);\n\
-    let b = fib_cache[n - 2];\n\
+    b = fib_cache[n - 2];\n\
   };\n\
   return a + b;\n\
 };\n\n\
 let counter = 0;\n\
 loop {\n\
   println("fib(", counter, ") = ", fib(counter));\n\
-  let counter = counter + 1;\n\
+  counter = counter + 1;\n\
   if counter > 28 { break; };\n\
 }';
   });
@@ -3,57 +3,146 @@ use std::any::Any;
 #[cfg(feature = "no_std")]
 use core::any::Any;
 
+#[cfg(not(feature = "no_std"))]
+use std::borrow::Cow;
+#[cfg(feature = "no_std")]
+use alloc::borrow::Cow;
+
+#[cfg(not(feature = "no_std"))]
+use std::fmt;
+#[cfg(feature = "no_std")]
+use core::fmt;
+
 #[cfg(not(feature = "no_std"))]
 use std::collections::HashMap;
 #[cfg(feature = "no_std")]
 use alloc::collections::BTreeMap;
 
+#[cfg(not(feature = "no_std"))]
+use std::cell::RefCell;
+#[cfg(feature = "no_std")]
+use core::cell::RefCell;
+
+#[cfg(not(feature = "no_std"))]
+use std::rc::{Rc, Weak};
+#[cfg(feature = "no_std")]
+use alloc::rc::{Rc, Weak};
+
 #[cfg(feature = "no_std")]
 use alloc::boxed::Box;
 #[cfg(feature = "no_std")]
+use alloc::string::String;
+#[cfg(feature = "no_std")]
 use alloc::vec::Vec;
 
-use interpreter::ScopeChain;
+use interpreter::{Scope, ScopeChain};
 
 // --- Types ---
 
+/// Number of arguments accepted by a Function or NativeFunction
+///
+///   - `min`: number of arguments that must be passed
+///   - `max`: greatest number of arguments that may be passed, or None if there is no limit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Arity {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+/// Function that can be called from a script
+///
+/// Held by Value::Func so that script Functions and NativeFunctions can be stored in variables,
+/// passed as arguments and returned from other Functions. Two Callables are equal only if they
+/// refer to the same Function.
+#[derive(Clone)]
+pub enum Callable<'src> {
+    Func(Rc<Function<'src>>),
+    Native(Rc<dyn NativeFunction>),
+}
+
 /// Result of executing an Executable
 #[derive(Clone, Debug, PartialEq)]
 pub enum ExecResult<'src> {
     Break,
-    Error(&'static str),
+    Continue,
+    Error(RuntimeError<'src>),
     None,
+    ParseError(ParseError<'src>),
     Return(Value<'src>),
 }
 
 /// Language expression
 ///
+/// An ExprKind along with the Span of source code from which it was parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr<'src> {
+    pub kind: ExprKind<'src>,
+    pub span: Span,
+}
+
+/// Kinds of language expression
+///
 /// Numbers, strings, lists, function calls, identifiers and operations thereon. Anything that can
 /// be evaluated to a Value.
+///
+/// A ListElement holds the collection Expr and the index Expr, so that chains such as "a[1][2]"
+/// are nested ListElements. Member access ("a.key") is a ListElement with a Str index.
+///
+/// A FuncCall calls a Function by name, whereas a Call calls the result of any other Expr (e.g.
+/// "f(1)(2)" or "handlers[0](x)"). Both hold the positional argument Exprs followed by the named
+/// argument Exprs (e.g. "port: 80") in the order in which they were passed.
+///
+/// A Lambda holds the parameters, rest parameter Ident (if any) and StmtBlock of an anonymous
+/// Function (see Function).
 #[derive(Clone, Debug, PartialEq)]
-pub enum Expr<'src> {
+pub enum ExprKind<'src> {
     BinOp(Box<Expr<'src>>, Opcode, Box<Expr<'src>>),
     Bool(bool),
-    Dict(Vec<(Ident<'src>, Box<Expr<'src>>)>),
-    FuncCall(Ident<'src>, Vec<Box<Expr<'src>>>),
+    Call(Box<Expr<'src>>, Vec<Box<Expr<'src>>>, Vec<(Ident<'src>, Box<Expr<'src>>)>),
+    Dict(Vec<(Cow<'src, str>, Box<Expr<'src>>)>),
+    FuncCall(Ident<'src>, Vec<Box<Expr<'src>>>, Vec<(Ident<'src>, Box<Expr<'src>>)>),
     Id(Ident<'src>),
-    Int(isize),
-    ListElement(Ident<'src>, Box<Expr<'src>>),
+    If(Vec<(Expr<'src>, Expr<'src>)>, Box<Expr<'src>>),
+    Int(i64),
+    Lambda(Vec<Param<'src>>, Option<Ident<'src>>, StmtBlock<'src>),
+    ListElement(Box<Expr<'src>>, Box<Expr<'src>>),
     List(Vec<Box<Expr<'src>>>),
     None,
     Real(f64),
-    Str(&'src str),
+    Str(Cow<'src, str>),
     UnaryOp(Opcode, Box<Expr<'src>>),
 }
 
 /// Script-defined functions
 ///
 /// Contains a list of statements (StmtBlock) that are executed when the Function is called, and a
-/// list of argument Idents that will be assigned to actual values during the call.
-#[derive(Debug)]
+/// list of parameters (Param) that will be assigned to actual values during the call. A
+/// parameter that is not passed an argument is assigned its default value. If `rest` is present,
+/// it is assigned a List of any positional arguments passed after those for `args`.
+///
+/// Arguments are assigned to `args` in order, followed by named arguments, which are assigned to
+/// the parameter with the same Ident.
+///
+/// `env` holds the Scopes in which the Function was created (other than the global Scope). They
+/// are shared with the Function rather than copied, so the Function sees later changes to their
+/// variables, and its own changes are visible to other Functions capturing the same Scopes, even
+/// after the Function call that created them has returned.
+///
+/// A Function stored in the Scope in which it was created (declared with `fn name() {}`, or
+/// assigned to a variable of that Scope) holds that Scope only weakly as its `home` to avoid a
+/// reference cycle. Whenever it is used as a Value, a copy is made with `home` added to `env`,
+/// which keeps the Scope alive for as long as the Value exists. The copy is cached in `bound` to
+/// avoid making another while it exists. All copies share the `identity` of the Function, so they
+/// compare equal. These fields are managed by the interpreter: create a Function with
+/// Function::new().
 pub struct Function<'src> {
-    pub args:  Vec<Ident<'src>>,
-    pub stmts: StmtBlock<'src>,
+    pub args:            Vec<Param<'src>>,
+    pub rest:            Option<Ident<'src>>,
+    pub stmts:           StmtBlock<'src>,
+    pub(crate) env:      Vec<Rc<RefCell<Scope<'src>>>>,
+    pub(crate) home:     Option<Weak<RefCell<Scope<'src>>>>,
+    pub(crate) bound:    RefCell<Weak<Function<'src>>>,
+    pub(crate) identity: Rc<()>,
 }
 
 /// Language identifier
@@ -61,15 +150,29 @@ pub struct Function<'src> {
 /// Used to represent a variable or function name.
 pub type Ident<'src> = &'src str;
 
+/// Function parameter
+///
+/// A parameter with a `default` Expr may be omitted from a call, in which case the Expr is
+/// evaluated when the Function is called, after the preceding parameters have been assigned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param<'src> {
+    pub id:      Ident<'src>,
+    pub default: Option<Expr<'src>>,
+}
+
 /// Operation codes
 ///
 /// Contains variants representing various operations that can be performed on expressions, such as
-/// arithmetic, logical and relational.
+/// arithmetic, logical and relational. BitNot, Neg, Not and Pos are unary operations.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Opcode {
     Add,
+    BitAnd,
+    BitNot,
+    BitOr,
     Div,
     Equal,
+    FloorDiv,
     GreaterThan,
     GreaterThanOrEqual,
     LessThan,
@@ -79,26 +182,120 @@ pub enum Opcode {
     LogicalXor,
     Mod,
     Mul,
+    Neg,
     Not,
     NotEqual,
+    Pos,
+    Pow,
+    Shl,
+    Shr,
     Sub,
 }
 
-/// Language statements
+/// Error produced when source code cannot be parsed
+///
+///   - `offset`: byte offset of the error within the source
+///   - `line`, `column`: 1-based position of the error (column counted in chars)
+///   - `snippet`: offending source text, from the error position to the end of its line
+///   - `expected`: description of what the parser expected to find at that position
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError<'src> {
+    pub offset:   usize,
+    pub line:     usize,
+    pub column:   usize,
+    pub snippet:  &'src str,
+    pub expected: &'static str,
+}
+
+/// Error produced when a program fails during execution
+///
+///   - `kind`: category of the error
+///   - `message`: description of the error
+///   - `span`: location of the Expr or Stmt that failed
+///   - `stack`: script Function calls active when the error occurred, innermost first
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuntimeError<'src> {
+    pub kind:    RuntimeErrorKind,
+    pub message: String,
+    pub span:    Span,
+    pub stack:   Vec<StackFrame<'src>>,
+}
+
+/// Categories of RuntimeError
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RuntimeErrorKind {
+    ArityMismatch,
+    DivisionByZero,
+    DuplicateDefinition,
+    InvalidArgument,
+    InvalidIndex,
+    Overflow,
+    TypeMismatch,
+    UndefinedFunction,
+    UndefinedVariable,
+    Unsupported,
+}
+
+/// Location of a language element within source code
+///
+///   - `start`, `end`: byte offsets of the element's first char and of the char following it
+///   - `line`, `column`: 1-based position of `start` (column counted in chars)
+///
+/// The default Span (line 0) represents an unknown location. Tests can compare ASTs built without
+/// location information with parsed ASTs after resetting the parsed Spans, see StripSpans.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub start:  usize,
+    pub end:    usize,
+    pub line:   usize,
+    pub column: usize,
+}
+
+/// Script Function call within a RuntimeError's stack
+///
+///   - `func`: Ident of the called Function
+///   - `span`: location of the call
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackFrame<'src> {
+    pub func: Ident<'src>,
+    pub span: Span,
+}
+
+/// Language statement
+///
+/// A StmtKind along with the Span of source code from which it was parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stmt<'src> {
+    pub kind: StmtKind<'src>,
+    pub span: Span,
+}
+
+/// Kinds of language statement
 ///
 /// Any single program instruction, such as a variable assignment, function call, conditional,
 /// loop.
+///
+/// A FnDef holds the Ident of the Function followed by the same items as ExprKind::Lambda.
+///
+/// Assignments hold the Opcode of a compound assignment (e.g. Opcode::Add for "+="), or None for a
+/// plain assignment. A ListItemAssignment holds the chain of index Exprs leading from the variable
+/// to the assigned item (e.g. [1, 2] for "a[1][2] = x").
 #[derive(Clone, Debug, PartialEq)]
-pub enum Stmt<'src> {
+pub enum StmtKind<'src> {
+    Assign(Ident<'src>, Option<Opcode>, Expr<'src>),
+    Block(StmtBlock<'src>),
     Break,
+    Continue,
     Expr(Expr<'src>),
-    FnDef(Ident<'src>, Vec<Ident<'src>>, StmtBlock<'src>),
-    If(Expr<'src>, StmtBlock<'src>),
-    IfElse(Expr<'src>, StmtBlock<'src>, StmtBlock<'src>),
+    FnDef(Ident<'src>, Vec<Param<'src>>, Option<Ident<'src>>, StmtBlock<'src>),
+    ForIn(Ident<'src>, Expr<'src>, StmtBlock<'src>),
+    ForRange(Ident<'src>, Expr<'src>, Expr<'src>, StmtBlock<'src>),
+    If(Vec<(Expr<'src>, StmtBlock<'src>)>, Option<StmtBlock<'src>>),
     Let(Ident<'src>, Expr<'src>),
-    ListItemAssignment(Ident<'src>, Expr<'src>, Expr<'src>),
+    ListItemAssignment(Ident<'src>, Vec<Expr<'src>>, Option<Opcode>, Expr<'src>),
     Loop(StmtBlock<'src>),
     Return(Expr<'src>),
+    While(Expr<'src>, StmtBlock<'src>),
 }
 
 /// Statement block
@@ -107,28 +304,329 @@ pub enum Stmt<'src> {
 pub type StmtBlock<'src> = Vec<Stmt<'src>>;
 
 /// Result of evaluating an Evaluatable
+///
+/// Ints are 64-bit on every target, so that scripts behave the same regardless of pointer width.
+///
+/// Strings (and dict keys) borrow their text from the source code where possible, e.g. for string
+/// literals without escape sequences, and own it otherwise.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value<'src> {
     Bool(bool),
 
     #[cfg(feature = "no_std")]
-    Dict(BTreeMap<Ident<'src>, Value<'src>>),
+    Dict(BTreeMap<Cow<'src, str>, Value<'src>>),
 
     #[cfg(not(feature = "no_std"))]
-    Dict(HashMap<Ident<'src>, Value<'src>>),
+    Dict(HashMap<Cow<'src, str>, Value<'src>>),
 
-    Int(isize),
+    Func(Callable<'src>),
+    Int(i64),
     List(Vec<Value<'src>>),
     None,
     Real(f64),
-    Str(&'src str),
+    Str(Cow<'src, str>),
+}
+
+// --- Implementations ---
+
+impl Arity {
+    /// Creates an Arity accepting exactly `n` arguments
+    pub fn exact(n: usize) -> Arity {
+        Arity { min: n, max: Some(n) }
+    }
+
+    /// Creates an Arity accepting `n` or more arguments
+    pub fn at_least(n: usize) -> Arity {
+        Arity { min: n, max: None }
+    }
+
+    /// Returns true if `n` arguments may be passed
+    pub fn accepts(&self, n: usize) -> bool {
+        n >= self.min && self.max.is_none_or(|max| n <= max)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = if self.min == 1 { "" } else { "s" };
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{} argument{}", max, s),
+            Some(max) => write!(f, "{} to {} arguments", self.min, max),
+            None      => write!(f, "at least {} argument{}", self.min, s),
+        }
+    }
+}
+
+impl<'src> fmt::Debug for Callable<'src> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Callable::Func(func) => {
+                let mut params = func.args.iter().map(|x| String::from(x.id)).collect::<Vec<String>>();
+                if let Some(rest) = func.rest {
+                    params.push(format!("...{}", rest));
+                }
+                write!(f, "fn({})", params.join(", "))
+            },
+            Callable::Native(_)  => write!(f, "native fn"),
+        }
+    }
+}
+
+impl<'src> fmt::Debug for Function<'src> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("args", &self.args)
+            .field("rest", &self.rest)
+            .field("stmts", &self.stmts)
+            .finish()
+    }
+}
+
+impl<'src> From<Ident<'src>> for Param<'src> {
+    /// Creates a Param without a default value
+    fn from(id: Ident<'src>) -> Param<'src> {
+        Param { id, default: None }
+    }
+}
+
+impl<'src> PartialEq for Callable<'src> {
+    fn eq(&self, other: &Callable<'src>) -> bool {
+        match (self, other) {
+            (Callable::Func(a),   Callable::Func(b))   => Rc::ptr_eq(&a.identity, &b.identity),
+            (Callable::Native(a), Callable::Native(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl<'src> ParseError<'src> {
+    /// Creates a ParseError for the position `offset` within `src`, calculating the line, column
+    /// and snippet of offending source
+    pub fn new(src: &'src str, offset: usize, expected: &'static str) -> ParseError<'src> {
+        let before = &src[..offset];
+        let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+        let line_end = src[offset..].find('\n').map_or(src.len(), |x| offset + x);
+        ParseError {
+            offset,
+            line:     before.matches('\n').count() + 1,
+            column:   before[line_start..].chars().count() + 1,
+            snippet:  src[offset..line_end].trim_end(),
+            expected,
+        }
+    }
+}
+
+impl<'src> fmt::Display for ParseError<'src> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: expected {}, found ",
+            self.line, self.column, self.expected
+        )?;
+        if self.snippet.is_empty() {
+            write!(f, "end of input")
+        } else {
+            write!(f, "`{}`", self.snippet)
+        }
+    }
+}
+
+impl<'src> RuntimeError<'src> {
+    /// Creates a RuntimeError with an unknown location and an empty stack
+    pub fn new<S: Into<String>>(kind: RuntimeErrorKind, message: S) -> RuntimeError<'src> {
+        RuntimeError {
+            kind,
+            message: message.into(),
+            span:    Span::default(),
+            stack:   vec![],
+        }
+    }
+
+    /// Sets the location of the error to `span` unless it is already known
+    pub fn at(mut self, span: Span) -> RuntimeError<'src> {
+        if self.span.is_unknown() {
+            self.span = span;
+        }
+        self
+    }
+
+    /// Adds a call of the Function `func` at `span` to the error's stack
+    pub fn called_from(mut self, func: Ident<'src>, span: Span) -> RuntimeError<'src> {
+        self.stack.push(StackFrame { func, span });
+        self
+    }
+}
+
+impl<'src> fmt::Display for RuntimeError<'src> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.span.is_unknown() {
+            write!(f, "line {}, column {}: ", self.span.line, self.span.column)?;
+        }
+        write!(f, "{}", self.message)?;
+        for frame in &self.stack {
+            write!(
+                f,
+                "\n  in {}() called at line {}, column {}",
+                frame.func, frame.span.line, frame.span.column
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'src> Expr<'src> {
+    /// Creates an Expr of the given kind located at `span`
+    pub fn new(kind: ExprKind<'src>, span: Span) -> Expr<'src> {
+        Expr { kind, span }
+    }
+}
+
+impl<'src> From<ExprKind<'src>> for Expr<'src> {
+    /// Creates an Expr of the given kind with an unknown location
+    fn from(kind: ExprKind<'src>) -> Expr<'src> {
+        Expr::new(kind, Span::default())
+    }
+}
+
+impl Span {
+    /// Returns true if this Span does not represent a known location
+    pub fn is_unknown(&self) -> bool {
+        self.line == 0
+    }
+
+    /// Returns a Span from the start of this Span to the end of `other`
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            end: other.end,
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+impl<'src> StripSpans for Expr<'src> {
+    fn strip_spans(self) -> Expr<'src> {
+        let kind = match self.kind {
+            ExprKind::BinOp(l, opc, r) => ExprKind::BinOp(l.strip_spans(), opc, r.strip_spans()),
+            ExprKind::Call(func, args, named) => {
+                ExprKind::Call(func.strip_spans(), args.strip_spans(), named.strip_spans())
+            },
+            ExprKind::Dict(items) => ExprKind::Dict(items.strip_spans()),
+            ExprKind::FuncCall(id, args, named) => ExprKind::FuncCall(id, args.strip_spans(), named.strip_spans()),
+            ExprKind::If(branches, else_x) => ExprKind::If(
+                branches.into_iter().map(|(c, x)| (c.strip_spans(), x.strip_spans())).collect(),
+                else_x.strip_spans(),
+            ),
+            ExprKind::Lambda(params, rest, stmts) => ExprKind::Lambda(params.strip_spans(), rest, stmts.strip_spans()),
+            ExprKind::ListElement(coll, idx) => ExprKind::ListElement(coll.strip_spans(), idx.strip_spans()),
+            ExprKind::List(items) => ExprKind::List(items.strip_spans()),
+            ExprKind::UnaryOp(opc, x) => ExprKind::UnaryOp(opc, x.strip_spans()),
+            kind => kind,
+        };
+        kind.into()
+    }
+}
+
+#[cfg(test)]
+impl<'src> StripSpans for Param<'src> {
+    fn strip_spans(self) -> Param<'src> {
+        Param { id: self.id, default: self.default.strip_spans() }
+    }
+}
+
+#[cfg(test)]
+impl<'src> StripSpans for Stmt<'src> {
+    fn strip_spans(self) -> Stmt<'src> {
+        let kind = match self.kind {
+            StmtKind::Assign(id, opc, x) => StmtKind::Assign(id, opc, x.strip_spans()),
+            StmtKind::Block(stmts) => StmtKind::Block(stmts.strip_spans()),
+            StmtKind::Expr(x) => StmtKind::Expr(x.strip_spans()),
+            StmtKind::FnDef(id, params, rest, stmts) => {
+                StmtKind::FnDef(id, params.strip_spans(), rest, stmts.strip_spans())
+            },
+            StmtKind::ForIn(id, x, stmts) => StmtKind::ForIn(id, x.strip_spans(), stmts.strip_spans()),
+            StmtKind::ForRange(id, start, end, stmts) => {
+                StmtKind::ForRange(id, start.strip_spans(), end.strip_spans(), stmts.strip_spans())
+            },
+            StmtKind::If(branches, else_stmts) => StmtKind::If(
+                branches.into_iter().map(|(c, x)| (c.strip_spans(), x.strip_spans())).collect(),
+                else_stmts.strip_spans(),
+            ),
+            StmtKind::Let(id, x) => StmtKind::Let(id, x.strip_spans()),
+            StmtKind::ListItemAssignment(id, path, opc, x) => {
+                StmtKind::ListItemAssignment(id, path.strip_spans(), opc, x.strip_spans())
+            },
+            StmtKind::Loop(stmts) => StmtKind::Loop(stmts.strip_spans()),
+            StmtKind::Return(x) => StmtKind::Return(x.strip_spans()),
+            StmtKind::While(x, stmts) => StmtKind::While(x.strip_spans(), stmts.strip_spans()),
+            kind => kind,
+        };
+        kind.into()
+    }
+}
+
+#[cfg(test)]
+impl<T: StripSpans> StripSpans for Box<T> {
+    fn strip_spans(self) -> Box<T> {
+        Box::new((*self).strip_spans())
+    }
+}
+
+#[cfg(test)]
+impl<T: StripSpans> StripSpans for Option<T> {
+    fn strip_spans(self) -> Option<T> {
+        self.map(StripSpans::strip_spans)
+    }
+}
+
+#[cfg(test)]
+impl<T: StripSpans, E> StripSpans for Result<T, E> {
+    fn strip_spans(self) -> Result<T, E> {
+        self.map(StripSpans::strip_spans)
+    }
+}
+
+#[cfg(test)]
+impl<T: StripSpans> StripSpans for Vec<T> {
+    fn strip_spans(self) -> Vec<T> {
+        self.into_iter().map(StripSpans::strip_spans).collect()
+    }
+}
+
+// Strips the Spans of the second item only, e.g. the result of a parser or a named argument
+#[cfg(test)]
+impl<A, T: StripSpans> StripSpans for (A, T) {
+    fn strip_spans(self) -> (A, T) {
+        (self.0, self.1.strip_spans())
+    }
+}
+
+impl<'src> Stmt<'src> {
+    /// Creates a Stmt of the given kind located at `span`
+    pub fn new(kind: StmtKind<'src>, span: Span) -> Stmt<'src> {
+        Stmt { kind, span }
+    }
+}
+
+impl<'src> From<StmtKind<'src>> for Stmt<'src> {
+    /// Creates a Stmt of the given kind with an unknown location
+    fn from(kind: StmtKind<'src>) -> Stmt<'src> {
+        Stmt::new(kind, Span::default())
+    }
 }
 
 // --- Traits ---
 
+/// Resets every Span within an AST (or a parser result holding one) to the unknown Span, so that
+/// tests can compare ASTs built without location information with parsed ASTs
+#[cfg(test)]
+pub trait StripSpans {
+    fn strip_spans(self) -> Self;
+}
+
 /// Trait allowing various language elements to be evaluated
 pub trait Evaluatable<'src> {
-    fn eval(&self, scopes: &mut ScopeChain<'src>) -> Value<'src>;
+    fn eval(&self, scopes: &mut ScopeChain<'src>) -> Result<Value<'src>, RuntimeError<'src>>;
 }
 
 /// Trait allowing various language elements to be executed
@@ -139,8 +637,31 @@ pub trait Executable<'src> {
 /// Trait used to allow structs to be called from a script
 ///
 /// The `execute()` method will be called via the script interpreter with the current ScopeChain
-/// and a list of argument values.
+/// and a list of argument values. The number of argument values is checked against `arity()`
+/// beforehand, which by default accepts any number of arguments.
+///
+/// Named arguments are only accepted if their names are returned by `named_params()`, in which
+/// case the interpreter calls `execute_named()` with the argument values and a list of named
+/// argument values instead. By default, no names are accepted and `execute_named()` calls
+/// `execute()`.
 pub trait NativeFunction {
     fn execute<'src>(&self, scopes: &mut ScopeChain<'src>, args: &[Value<'src>]) -> Value<'src>;
     fn as_any(&self) -> &dyn Any;
+
+    fn arity(&self) -> Arity {
+        Arity::at_least(0)
+    }
+
+    fn named_params(&self) -> &[&'static str] {
+        &[]
+    }
+
+    fn execute_named<'src>(
+        &self,
+        scopes: &mut ScopeChain<'src>,
+        args: &[Value<'src>],
+        _named: &[(Ident<'src>, Value<'src>)],
+    ) -> Value<'src> {
+        self.execute(scopes, args)
+    }
 }
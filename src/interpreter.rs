@@ -1,17 +1,38 @@
+#[cfg(not(feature = "no_std"))]
+use std::borrow::Cow;
+#[cfg(feature = "no_std")]
+use alloc::borrow::Cow;
+
 #[cfg(not(feature = "no_std"))]
 use std::collections::HashMap;
 #[cfg(feature = "no_std")]
 use alloc::collections::BTreeMap;
 
 #[cfg(not(feature = "no_std"))]
-use std::rc::Rc;
+use std::cell::RefCell;
 #[cfg(feature = "no_std")]
-use alloc::rc::Rc;
+use core::cell::RefCell;
+
+#[cfg(not(feature = "no_std"))]
+use std::cmp;
 #[cfg(feature = "no_std")]
-use alloc::vec::Vec;
+use core::cmp;
 
+#[cfg(not(feature = "no_std"))]
+use std::convert::TryFrom;
+#[cfg(feature = "no_std")]
+use core::convert::TryFrom;
+
+#[cfg(not(feature = "no_std"))]
+use std::rc::{Rc, Weak};
+#[cfg(feature = "no_std")]
+use alloc::rc::{Rc, Weak};
 #[cfg(feature = "no_std")]
-use core::f64::EPSILON;
+use alloc::boxed::Box;
+#[cfg(feature = "no_std")]
+use alloc::string::{String, ToString};
+#[cfg(feature = "no_std")]
+use alloc::vec::Vec;
 
 
 #[cfg(feature = "no_std")]
@@ -36,21 +57,131 @@ impl CoreAbs for f64 {
     }
 }
 
+#[cfg(feature = "no_std")]
+/*
+ * When not using the standard library, the f64::floor() and f64::powf() methods are not
+ * available either, so they are implemented here in the same way as CoreAbs.
+ *
+ * powf() only supports finite integral exponents. Opcode::eval() raises a RuntimeError for any
+ * other exponent rather than calling it.
+ */
+trait CoreFloat {
+    fn floor(self) -> f64;
+    fn powf(self, n: f64) -> f64;
+}
 
-#[cfg(not(feature = "no_std"))]
-use std::f64::EPSILON;
+#[cfg(feature = "no_std")]
+impl CoreFloat for f64 {
+    fn floor(self) -> f64 {
+        // Values of this magnitude (and infinities and NaN) have no fractional part
+        if !(self > -4_503_599_627_370_496f64 && self < 4_503_599_627_370_496f64) {
+            return self;
+        }
+        let t = self as i64 as f64;
+        if t > self {
+            t - 1f64
+        } else {
+            t
+        }
+    }
+
+    fn powf(self, n: f64) -> f64 {
+        // Exponentiation by squaring, halving the exponent as an f64 so that exponents of any
+        // magnitude are supported
+        let (mut base, mut exp, mut res) = (self, n.abs(), 1f64);
+        while exp >= 1f64 {
+            let half = (exp / 2f64).floor();
+            if exp - half * 2f64 >= 1f64 {
+                res *= base;
+            }
+            base *= base;
+            exp = half;
+        }
+        if n < 0f64 {
+            1f64 / res
+        } else {
+            res
+        }
+    }
+}
 
 
 use ast::{
-    Evaluatable, ExecResult, Executable, Expr, Function, Ident, NativeFunction, Opcode, Stmt,
-    StmtBlock, Value,
+    Arity, Callable, Evaluatable, ExecResult, Executable, Expr, ExprKind, Function, Ident, NativeFunction, Opcode,
+    Param, RuntimeError, RuntimeErrorKind, StackFrame, Stmt, StmtBlock, StmtKind, Value,
 };
 
+/*
+ * eval_or_return!(expr, scopes): evaluates an Evaluatable within Executable::exec(), returning
+ * ExecResult::Error from the enclosing function if evaluation fails.
+ */
+macro_rules! eval_or_return (
+    ($x:expr, $scopes:expr) => (
+        match $x.eval($scopes) {
+            Ok(x)  => x,
+            Err(e) => return ExecResult::Error(e),
+        }
+    );
+);
+
+/// Integer arithmetic modes
+///
+/// Determines the result of an integer operation whose result does not fit in an Int (e.g.
+/// "9223372036854775807 + 1"):
+///
+///   - `Checked`: the operation produces a RuntimeError
+///   - `Wrapping`: the result wraps around at the bounds of an Int
+///   - `Saturating`: the result is clamped to the bounds of an Int
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Arithmetic {
+    #[default]
+    Checked,
+    Saturating,
+    Wrapping,
+}
+impl Arithmetic {
+    /// Returns the result of an integer operation given its checked, wrapping and saturating
+    /// forms, or None if it overflowed in Checked mode
+    fn pick(self, checked: Option<i64>, wrapping: i64, saturating: i64) -> Option<i64> {
+        match self {
+            Arithmetic::Checked    => checked,
+            Arithmetic::Saturating => Some(saturating),
+            Arithmetic::Wrapping   => Some(wrapping),
+        }
+    }
+}
+
+/// Interpreter configuration
+///
+///   - `strict`: when true, operations that would otherwise silently produce Value::None (e.g.
+///     comparing mismatched types, indexing a non-collection or reading a missing list item)
+///     produce RuntimeErrors instead
+///   - `arithmetic`: handling of integer overflow (see Arithmetic)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    pub strict:     bool,
+    pub arithmetic: Arithmetic,
+}
+impl Config {
+    /// Returns the lenient fallback for an invalid operation: Value::None, or the RuntimeError
+    /// produced by `err` in strict mode
+    fn fallback<'src, F>(&self, err: F) -> Result<Value<'src>, RuntimeError<'src>>
+    where
+        F: FnOnce() -> RuntimeError<'src>,
+    {
+        if self.strict {
+            Err(err())
+        } else {
+            Ok(Value::None)
+        }
+    }
+}
+
 /// Language scope struct
 ///
 /// Contains HashMaps mapping Idents to Functions, NativeFunctions and Values (variables) in the
 /// scope
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Scope<'src> {
 
     #[cfg(not(feature = "no_std"))]
@@ -103,106 +234,201 @@ impl<'src> Scope<'src> {
 ///
 ///   - A stack of Scopes.
 ///   - Contains methods to resolve variables, Functions, etc and to modify Scope items.
-///   - Each function call pushes a new Scope onto the current ScopeChain.
+///   - Each function call replaces all but the first (global) Scope with the Scopes captured by
+///     the Function, followed by a new Scope for its arguments.
 ///   - All evaluations/executions require a ScopeChain.
+///   - Holds the interpreter Config.
+///
+/// Scopes are shared with the Functions that capture them (see Function), so a Scope outlives
+/// the ScopeChain when a Function created within it is still reachable.
 #[derive(Default)]
 pub struct ScopeChain<'src> {
-    scopes: Vec<Scope<'src>>,
+    config: Config,
+    scopes: Vec<Rc<RefCell<Scope<'src>>>>,
 }
 impl<'src> ScopeChain<'src> {
     /// Creates an empty ScopeChain
     pub fn new() -> ScopeChain<'src> {
-        ScopeChain { scopes: vec![] }
+        ScopeChain {
+            config: Config::default(),
+            scopes: vec![],
+        }
     }
 
     /// Creates a new ScopeChain with a single root Scope
     pub fn from_scope(scope: Scope<'src>) -> ScopeChain<'src> {
         ScopeChain {
-            scopes: vec![scope],
+            config: Config::default(),
+            scopes: vec![Rc::new(RefCell::new(scope))],
         }
     }
 
+    /// Returns the interpreter Config
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Replaces the interpreter Config
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
     /// Pushes a new Scope onto the stack
     pub fn push(&mut self, scope: Scope<'src>) {
-        self.scopes.push(scope);
+        self.scopes.push(Rc::new(RefCell::new(scope)));
     }
 
     /// Pops the last Scope from the stack
+    ///
+    /// If the Scope is still captured by a Function, a copy of it is returned.
     pub fn pop(&mut self) -> Option<Scope<'src>> {
-        self.scopes.pop()
+        self.scopes.pop().map(|x| match Rc::try_unwrap(x) {
+            Ok(scope) => scope.into_inner(),
+            Err(x)    => x.borrow().clone(),
+        })
     }
 
-    /// Inserts a Function into the last Scope with the Ident `key`
-    pub fn insert_func(&mut self, key: &'src str, val: Function<'src>) {
-        match self.scopes.last_mut() {
-            Some(ref mut scope) => scope.funcs.insert(key, Rc::new(val)),
-            _ => None,
-        };
+    /// Returns the Scopes to be captured by a Function created at this point: all Scopes except
+    /// the global Scope, which is always the first Scope of the caller's chain
+    fn capture(&self) -> Vec<Rc<RefCell<Scope<'src>>>> {
+        self.scopes.iter().skip(1).cloned().collect()
     }
 
-    /// Inserts a Value `val` into the dict identified by `key` at index `idx`
-    pub fn insert_dict_item(&mut self, key: &'src str, idx: &'src str, val: Value<'src>) {
-        for scope in self.scopes.iter_mut().rev() {
-            if let Some(ref mut scope_val) = scope.vars.get_mut(key) {
-                if let Value::Dict(ref mut dict) = scope_val {
-                    dict.insert(idx, val);
-                    break;
-                }
-            }
+    /// Replaces all Scopes but the global Scope with the captured Scopes `env`, followed by
+    /// `scope`, returning the replaced Scopes so that they can be restored by leave()
+    fn enter<I>(&mut self, env: I, scope: Scope<'src>) -> Vec<Rc<RefCell<Scope<'src>>>>
+    where
+        I: IntoIterator<Item = Rc<RefCell<Scope<'src>>>>,
+    {
+        let at = cmp::min(1, self.scopes.len());
+        let caller = self.scopes.split_off(at);
+        self.scopes.extend(env);
+        self.push(scope);
+        caller
+    }
+
+    /// Restores the Scopes replaced by enter(), releasing the Scope pushed by enter()
+    fn leave(&mut self, caller: Vec<Rc<RefCell<Scope<'src>>>>) {
+        if let Some(scope) = self.scopes.pop() {
+            release(scope);
         }
+        let at = cmp::min(1, self.scopes.len());
+        self.scopes.truncate(at);
+        self.scopes.extend(caller);
     }
 
-    /// Inserts a Value `val` into the list identified by `key` at index `idx`
-    pub fn insert_list_item(&mut self, key: &'src str, idx: usize, val: Value<'src>) {
-        for scope in self.scopes.iter_mut().rev() {
-            if let Some(ref mut scope_val) = scope.vars.get_mut(key) {
-                if let Value::List(ref mut lst) = scope_val {
-                    if lst.len() <= idx {
-                        lst.resize(idx + 1, Value::None);
-                    }
-                    lst[idx] = val;
-                    break;
-                }
+    /// Pops the last Scope from the stack, releasing it (see release())
+    fn pop_release(&mut self) {
+        if let Some(scope) = self.scopes.pop() {
+            release(scope);
+        }
+    }
+
+    /// Inserts a Function into the last Scope with the Ident `key`
+    pub fn insert_func(&mut self, key: &'src str, val: Function<'src>) {
+        if let Some(scope) = self.scopes.last() {
+            scope.borrow_mut().funcs.insert(key, Rc::new(val));
+        }
+    }
+
+    /// Searches from last to first Scope for a variable identified by `key` and updates its Value
+    /// to `val`
+    ///
+    /// Returns false if no variable identified by `key` was found.
+    pub fn update_var(&mut self, key: &'src str, val: Value<'src>) -> bool {
+        for scope in self.scopes.iter().rev() {
+            if scope.borrow().vars.contains_key(key) {
+                let val = Function::unbind(val, scope);
+                scope.borrow_mut().vars.insert(key, val);
+                return true;
             }
         }
+        false
     }
 
     /// Inserts or updates a Value for a variable identified by `key`
     pub fn insert_var(&mut self, key: &'src str, val: Value<'src>) {
-        match self.scopes.last_mut() {
-            Some(ref mut scope) => scope.vars.insert(key, val),
-            _ => None,
-        };
+        if let Some(scope) = self.scopes.last() {
+            let val = Function::unbind(val, scope);
+            scope.borrow_mut().vars.insert(key, val);
+        }
     }
 
     /// Searches from last to first Scope for a Function identified by `key` and returns a
     /// reference
     pub fn resolve_func(&self, key: &'src str) -> Option<Rc<Function<'src>>> {
         for scope in self.scopes.iter().rev() {
-            if let Some(x) = scope.funcs.get(key) {
+            if let Some(x) = scope.borrow().funcs.get(key) {
                 return Some(Rc::clone(x));
             }
         }
         None
     }
 
+    /// Searches from last to first Scope for a Function, NativeFunction or variable holding a
+    /// Value::Func identified by `key` and returns it as a Callable
+    ///
+    /// Within each Scope, Functions take precedence over NativeFunctions, which take precedence
+    /// over variables. Variables holding any other Value are ignored.
+    pub fn resolve_callable(&self, key: &'src str) -> Option<Callable<'src>> {
+        for scope in self.scopes.iter().rev() {
+            let scope = scope.borrow();
+            if let Some(x) = scope.funcs.get(key) {
+                return Some(Callable::Func(Rc::clone(x)));
+            }
+            if let Some(x) = scope.native_funcs.get(key) {
+                return Some(Callable::Native(Rc::clone(x)));
+            }
+            if let Some(Value::Func(x)) = scope.vars.get(key) {
+                return Some(x.clone());
+            }
+        }
+        None
+    }
+
     /// Searches from last to first Scope for a NativeFunction identified by `key` and returns a
     /// reference
     pub fn resolve_native_func(&self, key: &'src str) -> Option<Rc<dyn NativeFunction>> {
         for scope in self.scopes.iter().rev() {
-            if let Some(x) = scope.native_funcs.get(key) {
+            if let Some(x) = scope.borrow().native_funcs.get(key) {
                 return Some(Rc::clone(x));
             }
         }
         None
     }
 
-    /// Searches from last to first Scope for a variable identified by `key` and returns a
-    /// reference to its Value
-    pub fn resolve_var(&self, key: &'src str) -> Option<&Value<'src>> {
+    /// Searches from last to first Scope for a variable identified by `key` and returns a copy of
+    /// its Value
+    pub fn resolve_var(&self, key: &'src str) -> Option<Value<'src>> {
+        self.with_var(key, |x| match x {
+            Value::Func(Callable::Func(f)) => Value::Func(Callable::Func(Function::bind(f))),
+            x => x.clone(),
+        })
+    }
+
+    /// Searches from last to first Scope for a variable identified by `key` and returns the
+    /// result of calling `f` with a reference to its Value
+    pub fn with_var<F, R>(&self, key: &'src str, f: F) -> Option<R>
+    where
+        F: FnOnce(&Value<'src>) -> R,
+    {
         for scope in self.scopes.iter().rev() {
-            if let Some(ref x) = scope.vars.get(key) {
-                return Some(x);
+            if let Some(x) = scope.borrow().vars.get(key) {
+                return Some(f(x));
+            }
+        }
+        None
+    }
+
+    /// Searches from last to first Scope for a variable identified by `key` and returns the
+    /// result of calling `f` with a mutable reference to its Value
+    pub fn with_var_mut<F, R>(&mut self, key: &'src str, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Value<'src>) -> R,
+    {
+        for scope in self.scopes.iter().rev() {
+            if let Some(x) = scope.borrow_mut().vars.get_mut(key) {
+                return Some(f(x));
             }
         }
         None
@@ -211,14 +437,68 @@ impl<'src> ScopeChain<'src> {
 
 impl Opcode {
     /// Calculates an Opcode's integer result given left and right operands
-    fn calc_i(&self, l: isize, r: isize) -> isize {
+    ///
+    /// Results that do not fit in an Int are handled according to `mode`. Division or modulo by 0
+    /// is always a RuntimeError.
+    ///
+    ///   - FloorDiv: divides and rounds the quotient towards negative infinity
+    ///   - Pow: raises `l` to the power `r` (`r` must be at least 0)
+    fn calc_i<'src>(&self, mode: Arithmetic, l: i64, r: i64) -> Result<i64, RuntimeError<'src>> {
+        let mul = |a: i64, b: i64| mode.pick(a.checked_mul(b), a.wrapping_mul(b), a.saturating_mul(b));
+        let res = match *self {
+            Opcode::Add => mode.pick(l.checked_add(r), l.wrapping_add(r), l.saturating_add(r)),
+            Opcode::Mul => mul(l, r),
+            Opcode::Sub => mode.pick(l.checked_sub(r), l.wrapping_sub(r), l.saturating_sub(r)),
+            Opcode::FloorDiv | Opcode::Mod if r == 0 => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::DivisionByZero,
+                    format!("cannot apply {:?} with a divisor of 0", self),
+                ))
+            },
+            Opcode::FloorDiv => mode
+                .pick(l.checked_div(r), l.wrapping_div(r), l.saturating_div(r))
+                .map(|q| if l.wrapping_rem(r) != 0 && (l < 0) != (r < 0) { q - 1 } else { q }),
+
+            // Only i64::MIN % -1 overflows, and its remainder is 0
+            Opcode::Mod => Some(l.wrapping_rem(r)),
+
+            Opcode::Pow => {
+                let (mut base, mut exp, mut res) = (l, r, Some(1i64));
+                while let (true, Some(x)) = (exp > 0, res) {
+                    if exp & 1 == 1 {
+                        res = mul(x, base);
+                    }
+                    exp >>= 1;
+                    if exp > 0 {
+                        match mul(base, base) {
+                            Some(b) => base = b,
+                            None => res = None,
+                        }
+                    }
+                }
+                res
+            },
+            _ => Some(0),
+        };
+        res.ok_or_else(|| self.overflow(l, r))
+    }
+
+    /// Calculates an Opcode's bitwise result given left and right integer operands
+    ///
+    /// Right shifts are arithmetic (the sign is preserved). The shift amount must be at least 0
+    /// and less than the number of bits in an Int.
+    fn calc_bits<'src>(&self, l: i64, r: i64) -> Result<i64, RuntimeError<'src>> {
+        let valid_shift = r >= 0 && r < i64::BITS as i64;
         match *self {
-            Opcode::Add => l + r,
-            Opcode::Div => l / r,
-            Opcode::Mod => l % r,
-            Opcode::Mul => l * r,
-            Opcode::Sub => l - r,
-            _ => 0,
+            Opcode::BitAnd => Ok(l & r),
+            Opcode::BitOr  => Ok(l | r),
+            Opcode::Shl if valid_shift => Ok(l << r),
+            Opcode::Shr if valid_shift => Ok(l >> r),
+            Opcode::Shl | Opcode::Shr => Err(RuntimeError::new(
+                RuntimeErrorKind::Overflow,
+                format!("shift amount {} out of range", r),
+            )),
+            _ => Ok(0),
         }
     }
 
@@ -227,32 +507,81 @@ impl Opcode {
         match *self {
             Opcode::Add => l + r,
             Opcode::Div => l / r,
+            Opcode::FloorDiv => (l / r).floor(),
+            Opcode::Pow => l.powf(r),
             Opcode::Mul => l * r,
             Opcode::Sub => l - r,
             _ => 0f64,
         }
     }
 
+    /// Calculates an Opcode's string result given left and right operands, at least one of which
+    /// is a Str
+    ///
+    ///   - Add: concatenates the operands. An Int or Real operand is converted to text in the
+    ///     same way as when printed.
+    ///   - Mul: repeats the Str operand the number of times given by the Int operand (see
+    ///     repeat_text()). A negative count produces an empty Str.
+    fn calc_s<'src>(&self, l: &Value<'src>, r: &Value<'src>) -> Result<Value<'src>, RuntimeError<'src>> {
+        match (self, l, r) {
+            (Opcode::Add, _, _) => match (concat_text(l), concat_text(r)) {
+                (Some(l), Some(r)) => Ok(Value::Str(Cow::Owned(l + &r))),
+                _ => Err(self.type_mismatch(l, r)),
+            },
+            (Opcode::Mul, Value::Str(s), Value::Int(n))
+            | (Opcode::Mul, Value::Int(n), Value::Str(s)) => repeat_text(s, *n).map(|x| Value::Str(Cow::Owned(x))),
+            _ => Err(self.type_mismatch(l, r)),
+        }
+    }
+
     /// Evaluates the Opcode given left and right operands according to the operand types
-    fn eval<'src>(&self, l: Value<'src>, r: Value<'src>) -> Value<'src> {
+    fn eval<'src>(
+        &self,
+        config: &Config,
+        l: Value<'src>,
+        r: Value<'src>,
+    ) -> Result<Value<'src>, RuntimeError<'src>> {
         match *self {
             Opcode::Add | Opcode::Mul | Opcode::Sub => match (l, r) {
-                (Value::Int(l),  Value::Int(r))  => Value::Int(self.calc_i(l, r)),
-                (Value::Int(l),  Value::Real(r)) => Value::Real(self.calc_f(l as f64, r)),
-                (Value::Real(l), Value::Int(r))  => Value::Real(self.calc_f(l, r as f64)),
-                (Value::Real(l), Value::Real(r)) => Value::Real(self.calc_f(l, r)),
-                (_, _) => Value::None,
+                (Value::Int(l),  Value::Int(r))  => self.calc_i(config.arithmetic, l, r).map(Value::Int),
+                (Value::Int(l),  Value::Real(r)) => Ok(Value::Real(self.calc_f(l as f64, r))),
+                (Value::Real(l), Value::Int(r))  => Ok(Value::Real(self.calc_f(l, r as f64))),
+                (Value::Real(l), Value::Real(r)) => Ok(Value::Real(self.calc_f(l, r))),
+                (l, r) => self.calc_s(&l, &r),
             },
             Opcode::Div => match (l, r) {
-                (Value::Int(l),  Value::Int(r))  => Value::Real(self.calc_f(l as f64, r as f64)),
-                (Value::Int(l),  Value::Real(r)) => Value::Real(self.calc_f(l as f64, r)),
-                (Value::Real(l), Value::Int(r))  => Value::Real(self.calc_f(l, r as f64)),
-                (Value::Real(l), Value::Real(r)) => Value::Real(self.calc_f(l, r)),
-                (_, _) => Value::None,
+                (Value::Int(l),  Value::Int(r))  => Ok(Value::Real(self.calc_f(l as f64, r as f64))),
+                (Value::Int(l),  Value::Real(r)) => Ok(Value::Real(self.calc_f(l as f64, r))),
+                (Value::Real(l), Value::Int(r))  => Ok(Value::Real(self.calc_f(l, r as f64))),
+                (Value::Real(l), Value::Real(r)) => Ok(Value::Real(self.calc_f(l, r))),
+                (l, r) => Err(self.type_mismatch(&l, &r)),
             },
             Opcode::Mod => match (l, r) {
-                (Value::Int(l), Value::Int(r)) => Value::Int(self.calc_i(l, r)),
-                (_, _) => Value::None,
+                (Value::Int(l), Value::Int(r)) => self.calc_i(config.arithmetic, l, r).map(Value::Int),
+                (l, r) => Err(self.type_mismatch(&l, &r)),
+            },
+
+            // Without the standard library, only finite integral exponents are supported
+            #[cfg(feature = "no_std")]
+            Opcode::Pow if matches!(r, Value::Real(x) if x.floor() != x || x.abs() == f64::INFINITY) => {
+                Err(RuntimeError::new(
+                    RuntimeErrorKind::Unsupported,
+                    format!("cannot apply {:?} with exponent {:?} without std", self, r),
+                ))
+            },
+            Opcode::FloorDiv | Opcode::Pow => match (l, r) {
+                (Value::Int(l),  Value::Int(r)) if *self == Opcode::Pow && r < 0 => {
+                    Ok(Value::Real(self.calc_f(l as f64, r as f64)))
+                },
+                (Value::Int(l),  Value::Int(r))  => self.calc_i(config.arithmetic, l, r).map(Value::Int),
+                (Value::Int(l),  Value::Real(r)) => Ok(Value::Real(self.calc_f(l as f64, r))),
+                (Value::Real(l), Value::Int(r))  => Ok(Value::Real(self.calc_f(l, r as f64))),
+                (Value::Real(l), Value::Real(r)) => Ok(Value::Real(self.calc_f(l, r))),
+                (l, r) => Err(self.type_mismatch(&l, &r)),
+            },
+            Opcode::BitAnd | Opcode::BitOr | Opcode::Shl | Opcode::Shr => match (l, r) {
+                (Value::Int(l), Value::Int(r)) => self.calc_bits(l, r).map(Value::Int),
+                (l, r) => Err(self.type_mismatch(&l, &r)),
             },
             Opcode::Equal
             | Opcode::NotEqual
@@ -262,204 +591,537 @@ impl Opcode {
             | Opcode::GreaterThanOrEqual
             | Opcode::LogicalAnd
             | Opcode::LogicalOr
-            | Opcode::LogicalXor => self.logical(l, r),
+            | Opcode::LogicalXor => self.logical(config, l, r),
 
-            _ => Value::None,
+            Opcode::BitNot | Opcode::Neg | Opcode::Not | Opcode::Pos => {
+                Err(self.type_mismatch(&l, &r))
+            },
         }
     }
 
     /// Evaluates the unary Opcode given Value of the operand
-    fn eval_unary<'src>(&self, x: &Value<'src>) -> Value<'src> {
-        match *self {
-            Opcode::Not => match x {
-                Value::Bool(x) => Value::Bool(!x),
-                Value::None    => Value::Bool(true),
-                _              => Value::Bool(false),
-            },
-            _ => Value::None,
+    fn eval_unary<'src>(&self, config: &Config, x: &Value<'src>) -> Result<Value<'src>, RuntimeError<'src>> {
+        match (self, x) {
+            (Opcode::BitNot, Value::Int(x))  => Ok(Value::Int(!x)),
+            (Opcode::Neg,    Value::Int(x))  => config
+                .arithmetic
+                .pick(x.checked_neg(), x.wrapping_neg(), x.saturating_neg())
+                .map(Value::Int)
+                .ok_or_else(|| RuntimeError::new(
+                    RuntimeErrorKind::Overflow,
+                    format!("integer overflow applying {:?} to {}", self, x),
+                )),
+            (Opcode::Neg,    Value::Real(x)) => Ok(Value::Real(-x)),
+            (Opcode::Not,    x)              => Ok(Value::Bool(!x.is_truthy())),
+            (Opcode::Pos,    Value::Int(x))  => Ok(Value::Int(*x)),
+            (Opcode::Pos,    Value::Real(x)) => Ok(Value::Real(*x)),
+            _ => Err(RuntimeError::new(
+                RuntimeErrorKind::TypeMismatch,
+                format!("cannot apply {:?} to {}", self, x.type_name()),
+            )),
         }
     }
 
     /// Calculates an Opcode's logical result given left and right operands
-    fn logical<'src>(&self, l: Value<'src>, r: Value<'src>) -> Value<'src> {
-
+    ///
+    /// Operands that cannot be compared produce Value::None unless `config` is strict. Operands of
+    /// logical operators are converted to Bool according to their truthiness (see
+    /// Value::is_truthy()).
+    fn logical<'src>(
+        &self,
+        config: &Config,
+        l: Value<'src>,
+        r: Value<'src>,
+    ) -> Result<Value<'src>, RuntimeError<'src>> {
         match *self {
             Opcode::Equal => match (l, r) {
-                (Value::Int(l),  Value::Int(r))  => Value::Bool(l == r),
-                (Value::Int(l),  Value::Real(r)) => Value::Bool(((l as f64) - r).abs() <= EPSILON),
-                (Value::Real(l), Value::Int(r))  => Value::Bool((l - (r as f64)).abs() <= EPSILON),
-                (Value::Real(l), Value::Real(r)) => Value::Bool((l - r).abs() <= EPSILON),
-                (Value::Str(l),  Value::Str(r))  => Value::Bool(l == r),
-                (_, _) => Value::None,
+                (Value::Int(l),  Value::Int(r))  => Ok(Value::Bool(l == r)),
+                (Value::Int(l),  Value::Real(r)) => Ok(Value::Bool(((l as f64) - r).abs() <= f64::EPSILON)),
+                (Value::Real(l), Value::Int(r))  => Ok(Value::Bool((l - (r as f64)).abs() <= f64::EPSILON)),
+                (Value::Real(l), Value::Real(r)) => Ok(Value::Bool((l - r).abs() <= f64::EPSILON)),
+                (l, r) => Ok(Value::Bool(l == r)),
             },
             Opcode::NotEqual => match (l, r) {
-                (Value::Int(l),  Value::Int(r))  => Value::Bool(l != r),
-                (Value::Int(l),  Value::Real(r)) => Value::Bool(((l as f64) - r).abs() > EPSILON),
-                (Value::Real(l), Value::Int(r))  => Value::Bool((l - (r as f64)).abs() > EPSILON),
-                (Value::Real(l), Value::Real(r)) => Value::Bool((l - r).abs() > EPSILON),
-                (Value::Str(l),  Value::Str(r))  => Value::Bool(l != r),
-                (_, _) => Value::None,
+                (Value::Int(l),  Value::Int(r))  => Ok(Value::Bool(l != r)),
+                (Value::Int(l),  Value::Real(r)) => Ok(Value::Bool(((l as f64) - r).abs() > f64::EPSILON)),
+                (Value::Real(l), Value::Int(r))  => Ok(Value::Bool((l - (r as f64)).abs() > f64::EPSILON)),
+                (Value::Real(l), Value::Real(r)) => Ok(Value::Bool((l - r).abs() > f64::EPSILON)),
+                (l, r) => Ok(Value::Bool(l != r)),
             },
             Opcode::LessThan => match (l, r) {
-                (Value::Int(l),  Value::Int(r))  => Value::Bool(l < r),
-                (Value::Int(l),  Value::Real(r)) => Value::Bool((l as f64) < r),
-                (Value::Real(l), Value::Int(r))  => Value::Bool(l < r as f64),
-                (Value::Real(l), Value::Real(r)) => Value::Bool(l < r),
-                (Value::Str(l),  Value::Str(r))  => Value::Bool(l < r),
-                (_, _) => Value::None,
+                (Value::Int(l),  Value::Int(r))  => Ok(Value::Bool(l < r)),
+                (Value::Int(l),  Value::Real(r)) => Ok(Value::Bool((l as f64) < r)),
+                (Value::Real(l), Value::Int(r))  => Ok(Value::Bool(l < r as f64)),
+                (Value::Real(l), Value::Real(r)) => Ok(Value::Bool(l < r)),
+                (Value::Str(l),  Value::Str(r))  => Ok(Value::Bool(l < r)),
+                (l, r) => config.fallback(|| self.type_mismatch(&l, &r)),
             },
             Opcode::GreaterThan => match (l, r) {
-                (Value::Int(l),  Value::Int(r))  => Value::Bool(l > r),
-                (Value::Int(l),  Value::Real(r)) => Value::Bool(l as f64 > r),
-                (Value::Real(l), Value::Int(r))  => Value::Bool(l > r as f64),
-                (Value::Real(l), Value::Real(r)) => Value::Bool(l > r),
-                (Value::Str(l),  Value::Str(r))  => Value::Bool(l > r),
-                (_, _) => Value::None,
+                (Value::Int(l),  Value::Int(r))  => Ok(Value::Bool(l > r)),
+                (Value::Int(l),  Value::Real(r)) => Ok(Value::Bool(l as f64 > r)),
+                (Value::Real(l), Value::Int(r))  => Ok(Value::Bool(l > r as f64)),
+                (Value::Real(l), Value::Real(r)) => Ok(Value::Bool(l > r)),
+                (Value::Str(l),  Value::Str(r))  => Ok(Value::Bool(l > r)),
+                (l, r) => config.fallback(|| self.type_mismatch(&l, &r)),
             },
             Opcode::LessThanOrEqual => match (l, r) {
-                (Value::Int(l),  Value::Int(r))  => Value::Bool(l <= r),
-                (Value::Int(l),  Value::Real(r)) => Value::Bool(l as f64 <= r),
-                (Value::Real(l), Value::Int(r))  => Value::Bool(l <= r as f64),
-                (Value::Real(l), Value::Real(r)) => Value::Bool(l <= r),
-                (Value::Str(l),  Value::Str(r))  => Value::Bool(l <= r),
-                (_, _) => Value::None,
+                (Value::Int(l),  Value::Int(r))  => Ok(Value::Bool(l <= r)),
+                (Value::Int(l),  Value::Real(r)) => Ok(Value::Bool(l as f64 <= r)),
+                (Value::Real(l), Value::Int(r))  => Ok(Value::Bool(l <= r as f64)),
+                (Value::Real(l), Value::Real(r)) => Ok(Value::Bool(l <= r)),
+                (Value::Str(l),  Value::Str(r))  => Ok(Value::Bool(l <= r)),
+                (l, r) => config.fallback(|| self.type_mismatch(&l, &r)),
             },
             Opcode::GreaterThanOrEqual => match (l, r) {
-                (Value::Int(l),  Value::Int(r))  => Value::Bool(l >= r),
-                (Value::Int(l),  Value::Real(r)) => Value::Bool(l as f64 >= r),
-                (Value::Real(l), Value::Int(r))  => Value::Bool(l >= r as f64),
-                (Value::Real(l), Value::Real(r)) => Value::Bool(l >= r),
-                (Value::Str(l),  Value::Str(r))  => Value::Bool(l >= r),
-                (_, _) => Value::None,
-            },
-            Opcode::LogicalAnd => match (l, r) {
-                (Value::Bool(l), Value::Bool(r)) => Value::Bool(l && r),
-                (_, _) => Value::None,
+                (Value::Int(l),  Value::Int(r))  => Ok(Value::Bool(l >= r)),
+                (Value::Int(l),  Value::Real(r)) => Ok(Value::Bool(l as f64 >= r)),
+                (Value::Real(l), Value::Int(r))  => Ok(Value::Bool(l >= r as f64)),
+                (Value::Real(l), Value::Real(r)) => Ok(Value::Bool(l >= r)),
+                (Value::Str(l),  Value::Str(r))  => Ok(Value::Bool(l >= r)),
+                (l, r) => config.fallback(|| self.type_mismatch(&l, &r)),
             },
-            Opcode::LogicalOr => match (l, r) {
-                (Value::Bool(l), Value::Bool(r)) => Value::Bool(l || r),
-                (_, _) => Value::None,
-            },
-            Opcode::LogicalXor => match (l, r) {
-                (Value::Bool(l), Value::Bool(r)) => Value::Bool((l || r) && !(l && r)),
-                (_, _) => Value::None,
-            },
-            _ => Value::None,
+            Opcode::LogicalAnd => Ok(Value::Bool(l.is_truthy() && r.is_truthy())),
+            Opcode::LogicalOr  => Ok(Value::Bool(l.is_truthy() || r.is_truthy())),
+            Opcode::LogicalXor => Ok(Value::Bool(l.is_truthy() != r.is_truthy())),
+            _ => config.fallback(|| self.type_mismatch(&l, &r)),
         }
     }
+
+    /// Creates a RuntimeError for an Opcode whose integer result for operands `l` and `r` is out
+    /// of range
+    fn overflow<'src>(&self, l: i64, r: i64) -> RuntimeError<'src> {
+        RuntimeError::new(
+            RuntimeErrorKind::Overflow,
+            format!("integer overflow applying {:?} to {} and {}", self, l, r),
+        )
+    }
+
+    /// Creates a RuntimeError for an Opcode that cannot be applied to operands `l` and `r`
+    fn type_mismatch<'src>(&self, l: &Value<'src>, r: &Value<'src>) -> RuntimeError<'src> {
+        RuntimeError::new(
+            RuntimeErrorKind::TypeMismatch,
+            format!("cannot apply {:?} to {} and {}", self, l.type_name(), r.type_name()),
+        )
+    }
 }
 
 impl<'src> Function<'src> {
+    /// Creates a Function that captures no Scopes other than the global Scope
+    pub fn new(
+        args: Vec<Param<'src>>,
+        rest: Option<Ident<'src>>,
+        stmts: StmtBlock<'src>,
+    ) -> Function<'src> {
+        Function {
+            args,
+            rest,
+            stmts,
+            env:      vec![],
+            home:     None,
+            bound:    RefCell::new(Weak::new()),
+            identity: Rc::new(()),
+        }
+    }
+
+    /// Returns a copy of the Function with the same identity, capturing the Scopes `env` and
+    /// (weakly) `home`
+    fn with_env(
+        &self,
+        env: Vec<Rc<RefCell<Scope<'src>>>>,
+        home: Option<Weak<RefCell<Scope<'src>>>>,
+    ) -> Function<'src> {
+        Function {
+            args:     self.args.clone(),
+            rest:     self.rest,
+            stmts:    self.stmts.clone(),
+            env,
+            home,
+            bound:    RefCell::new(Weak::new()),
+            identity: Rc::clone(&self.identity),
+        }
+    }
+
+    /// Returns the number of arguments accepted by the Function
+    ///
+    /// Every parameter up to the last one without a default value must be passed an argument.
+    pub fn arity(&self) -> Arity {
+        let min = self.args.iter().rposition(|x| x.default.is_none()).map_or(0, |x| x + 1);
+        match self.rest {
+            Some(_) => Arity::at_least(min),
+            None    => Arity { min, max: Some(self.args.len()) },
+        }
+    }
+
+    /// Executes the Function with positional arguments only, see execute_named()
+    pub fn execute(
+        &self,
+        scopes: &mut ScopeChain<'src>,
+        args: &[Value<'src>],
+    ) -> Result<Value<'src>, RuntimeError<'src>> {
+        self.execute_named(scopes, args, &[])
+    }
+
     /// Executes the Function
     ///
+    ///   - Matches the positional arguments `args` and named arguments `named` to the Function's
+    ///     parameters
     ///   - Creates a new Function Scope
+    ///   - Replaces the caller's Scopes (except the global Scope) with the Function's captured
+    ///     Scopes and the Function Scope
+    ///   - Assigns the arguments (or default values) to the Function's parameters
     ///   - Executes the Function's statements (StmtBlock)
-    ///   - Removes the Function's Scope
-    ///   - Returns the Function result Value
-    pub fn execute(&self, scopes: &mut ScopeChain<'src>, args: &[Value<'src>]) -> Value<'src> {
-        // Create local scope
-        let scope = Scope::from_args(
-            &self
-                .args
-                .iter()
-                .zip(args)
-                .collect::<Vec<(&Ident, &Value)>>(),
-        );
-
-        // Push new function scope onto chain
-        scopes.push(scope);
-
-        // Evaluate Function StmtBlock
-        let res = match self.stmts.exec(scopes) {
-            ExecResult::Return(x) => x,
-            _ => Value::None,
-        };
+    ///   - Restores the caller's Scopes
+    ///   - Returns the Function result Value, or the RuntimeError that stopped its execution
+    pub fn execute_named(
+        &self,
+        scopes: &mut ScopeChain<'src>,
+        args: &[Value<'src>],
+        named: &[(Ident<'src>, Value<'src>)],
+    ) -> Result<Value<'src>, RuntimeError<'src>> {
+        self.execute_from(scopes, args, named, None)
+    }
+
+    /// Executes the Function as execute_named(), adding the call `frame` (if any) to the stack of
+    /// any RuntimeError raised once the Function has started, i.e. while assigning default values
+    /// or executing the Function's statements
+    fn execute_from(
+        &self,
+        scopes: &mut ScopeChain<'src>,
+        args: &[Value<'src>],
+        named: &[(Ident<'src>, Value<'src>)],
+        frame: Option<StackFrame<'src>>,
+    ) -> Result<Value<'src>, RuntimeError<'src>> {
+        let vals = self.match_args(args, named)?;
 
-        // Pop function Scope from chain
-        scopes.pop();
+        // Switch to the Function's environment
+        let home = self.home.as_ref().and_then(Weak::upgrade);
+        let caller = scopes.enter(self.env.iter().cloned().chain(home), Scope::new());
+
+        // Assign parameters and evaluate Function StmtBlock
+        let res = self.assign_params(scopes, vals, args).and_then(|_| match self.stmts.exec(scopes) {
+            ExecResult::Return(x) => Ok(x),
+            ExecResult::Error(e)  => Err(e),
+            _ => Ok(Value::None),
+        }).map_err(|e| match frame {
+            Some(frame) => e.at(frame.span).called_from(frame.func, frame.span),
+            None        => e,
+        });
+
+        // Switch back to the caller's environment
+        scopes.leave(caller);
 
         res
     }
+
+    /// Returns the Function `func` for use as a Value
+    ///
+    /// For a Function stored in a Scope that still exists, this is a copy with the same identity
+    /// holding that Scope strongly, which is reused while any Value of the Function exists.
+    fn bind(func: &Rc<Function<'src>>) -> Rc<Function<'src>> {
+        let home = match func.home.as_ref().and_then(Weak::upgrade) {
+            Some(x) => x,
+            None    => return Rc::clone(func),
+        };
+        if let Some(bound) = func.bound.borrow().upgrade() {
+            return bound;
+        }
+        let mut env = func.env.clone();
+        env.push(home);
+        let bound = Rc::new(func.with_env(env, None));
+        *func.bound.borrow_mut() = Rc::downgrade(&bound);
+        bound
+    }
+
+    /// Returns `val` for storing in a variable of `scope`
+    ///
+    /// A Function Value that captures `scope` as its last Scope (e.g. one created within `scope`)
+    /// is replaced by a copy holding `scope` weakly as its home, with `val` as its bound copy (see
+    /// Function), so that storing it does not create a reference cycle.
+    fn unbind(val: Value<'src>, scope: &Rc<RefCell<Scope<'src>>>) -> Value<'src> {
+        let func = match val {
+            Value::Func(Callable::Func(ref f)) if f.env.last().is_some_and(|x| Rc::ptr_eq(x, scope)) => f,
+            _ => return val,
+        };
+        let env = func.env[..func.env.len() - 1].to_vec();
+        let unbound = func.with_env(env, Some(Rc::downgrade(scope)));
+        *unbound.bound.borrow_mut() = Rc::downgrade(func);
+        Value::Func(Callable::Func(Rc::new(unbound)))
+    }
+
+    /// Returns the argument Value for each of the Function's parameters, or None where the
+    /// parameter's default value is to be used
+    ///
+    /// Positional arguments are matched to parameters in order and named arguments by Ident. A
+    /// RuntimeError is returned if the number of positional arguments is not accepted by the
+    /// Function's Arity, if a named argument does not match a parameter or matches a parameter
+    /// that already has an argument, or if a parameter without a default value has no argument.
+    fn match_args(
+        &self,
+        args: &[Value<'src>],
+        named: &[(Ident<'src>, Value<'src>)],
+    ) -> Result<Vec<Option<Value<'src>>>, RuntimeError<'src>> {
+        if named.is_empty() || (self.rest.is_none() && args.len() > self.args.len()) {
+            check_arity(self.arity(), args.len())?;
+        }
+        let mut vals = (0..self.args.len())
+            .map(|i| args.get(i).cloned())
+            .collect::<Vec<Option<Value<'src>>>>();
+        for (id, val) in named {
+            match self.args.iter().position(|x| x.id == *id) {
+                Some(i) if vals[i].is_some() => return Err(repeated_arg(id)),
+                Some(i) => vals[i] = Some(val.clone()),
+                None => return Err(unknown_arg(id)),
+            }
+        }
+        if let Some((param, _)) = self
+            .args
+            .iter()
+            .zip(&vals)
+            .find(|(param, val)| param.default.is_none() && val.is_none())
+        {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::ArityMismatch,
+                format!("missing argument '{}'", param.id),
+            ));
+        }
+        Ok(vals)
+    }
+
+    /// Inserts a variable into the Function Scope for each parameter, holding either its
+    /// argument Value from `vals` (see match_args()) or its default value, followed by the rest
+    /// parameter (if any) holding a List of the remaining positional arguments `args`
+    fn assign_params(
+        &self,
+        scopes: &mut ScopeChain<'src>,
+        vals: Vec<Option<Value<'src>>>,
+        args: &[Value<'src>],
+    ) -> Result<(), RuntimeError<'src>> {
+        for (param, val) in self.args.iter().zip(vals) {
+            let val = match (val, &param.default) {
+                (Some(x), _)       => x,
+                (None, Some(expr)) => expr.eval(scopes)?,
+                (None, None)       => Value::None,
+            };
+            scopes.insert_var(param.id, val);
+        }
+        if let Some(rest) = self.rest {
+            let extra = args.get(self.args.len()..).unwrap_or(&[]);
+            scopes.insert_var(rest, Value::List(extra.to_vec()));
+        }
+        Ok(())
+    }
+}
+
+impl<'src> Callable<'src> {
+    /// Returns the number of arguments accepted by the Function or NativeFunction
+    pub fn arity(&self) -> Arity {
+        match self {
+            Callable::Func(f)   => f.arity(),
+            Callable::Native(f) => f.arity(),
+        }
+    }
+
+    /// Calls the Function or NativeFunction with positional arguments only, see call_named()
+    pub fn call(
+        &self,
+        scopes: &mut ScopeChain<'src>,
+        args: &[Value<'src>],
+    ) -> Result<Value<'src>, RuntimeError<'src>> {
+        self.call_named(scopes, args, &[])
+    }
+
+    /// Calls the Function or NativeFunction with the positional argument values `args` and the
+    /// named argument values `named`
+    ///
+    /// Returns a RuntimeError if the arguments cannot be matched to the Callable's parameters:
+    /// for a NativeFunction, if the number of positional arguments is not accepted by its Arity,
+    /// or if a named argument is not accepted or is passed more than once.
+    pub fn call_named(
+        &self,
+        scopes: &mut ScopeChain<'src>,
+        args: &[Value<'src>],
+        named: &[(Ident<'src>, Value<'src>)],
+    ) -> Result<Value<'src>, RuntimeError<'src>> {
+        self.call_from(scopes, args, named, None)
+    }
+
+    /// Calls the Callable as call_named(), recording the call `frame` (if any) for a Function, see
+    /// Function::execute_from()
+    fn call_from(
+        &self,
+        scopes: &mut ScopeChain<'src>,
+        args: &[Value<'src>],
+        named: &[(Ident<'src>, Value<'src>)],
+        frame: Option<StackFrame<'src>>,
+    ) -> Result<Value<'src>, RuntimeError<'src>> {
+        match self {
+            Callable::Func(f)   => f.execute_from(scopes, args, named, frame),
+            Callable::Native(f) => {
+                check_arity(f.arity(), args.len())?;
+                for (i, (id, _)) in named.iter().enumerate() {
+                    if !f.named_params().contains(id) {
+                        return Err(unknown_arg(id));
+                    }
+                    if named[..i].iter().any(|x| x.0 == *id) {
+                        return Err(repeated_arg(id));
+                    }
+                }
+                Ok(f.execute_named(scopes, args, named))
+            },
+        }
+    }
+}
+
+impl<'src> Value<'src> {
+    /// Returns the truth value of the Value when used as a logical operand or as a condition:
+    /// Bool(false) and None are false, any other Value is true
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(x) => *x,
+            Value::None    => false,
+            _              => true,
+        }
+    }
+
+    /// Returns the name of the Value's type for use in error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "Bool",
+            Value::Dict(_) => "Dict",
+            Value::Func(_) => "Func",
+            Value::Int(_)  => "Int",
+            Value::List(_) => "List",
+            Value::None    => "None",
+            Value::Real(_) => "Real",
+            Value::Str(_)  => "Str",
+        }
+    }
 }
 
 impl<'src> Evaluatable<'src> for Expr<'src> {
     /// Evaluate an Expr
-    fn eval(&self, scopes: &mut ScopeChain<'src>) -> Value<'src> {
-        match *self {
-            Expr::BinOp(ref l, ref opc, ref r) => opc.eval(l.eval(scopes), r.eval(scopes)),
-            Expr::Bool(x) => Value::Bool(x),
-            Expr::Dict(ref items) => {
+    ///
+    /// Any RuntimeError produced by the Expr or its sub-expressions is located at the innermost
+    /// failing Expr.
+    fn eval(&self, scopes: &mut ScopeChain<'src>) -> Result<Value<'src>, RuntimeError<'src>> {
+        match self.kind {
+            ExprKind::BinOp(..) => eval_binops(self, scopes),
+            ExprKind::Bool(x) => Ok(Value::Bool(x)),
+            ExprKind::Call(ref func, ref args, ref named) => {
+                let func = match func.eval(scopes)? {
+                    Value::Func(f) => f,
+                    x => return Err(RuntimeError::new(
+                        RuntimeErrorKind::TypeMismatch,
+                        format!("cannot call {}", x.type_name()),
+                    ).at(func.span)),
+                };
+                let eval_args = args
+                    .iter()
+                    .map(|x| x.eval(scopes))
+                    .collect::<Result<Vec<Value<'src>>, RuntimeError<'src>>>()?;
+                let eval_named = eval_named(named, scopes)?;
+                let frame = StackFrame { func: "<anonymous>", span: self.span };
+                func.call_from(scopes, &eval_args, &eval_named, Some(frame))
+                    .map_err(|e| e.at(self.span))
+            },
+            ExprKind::Dict(ref items) => {
                 #[cfg(not(feature = "no_std"))]
-                let mut map = HashMap::<Ident, Value>::new();
+                let mut map = HashMap::<Cow<str>, Value>::new();
                 #[cfg(feature = "no_std")]
-                let mut map = BTreeMap::<Ident, Value>::new();
+                let mut map = BTreeMap::<Cow<str>, Value>::new();
                 for item in items.iter() {
-                    map.insert(item.0, item.1.eval(scopes));
+                    map.insert(item.0.clone(), item.1.eval(scopes)?);
                 }
-                Value::Dict(map)
+                Ok(Value::Dict(map))
             },
-            Expr::FuncCall(ref func_id, ref args) => {
-                let eval_args = args.iter().map(|x| x.eval(scopes)).collect::<Vec<Value<'src>>>();
-                match scopes.resolve_func(func_id) {
-                    Some(f) => f.execute(scopes, &eval_args),
-                    None => match scopes.resolve_native_func(func_id) {
-                        Some(f) => f.execute(scopes, &eval_args),
-                        None => Value::None,
-                    },
+            ExprKind::FuncCall(func_id, ref args, ref named) => {
+                let eval_args = args
+                    .iter()
+                    .map(|x| x.eval(scopes))
+                    .collect::<Result<Vec<Value<'src>>, RuntimeError<'src>>>()?;
+                let eval_named = eval_named(named, scopes)?;
+                let frame = StackFrame { func: func_id, span: self.span };
+                match scopes.resolve_callable(func_id) {
+                    Some(f) => f
+                        .call_from(scopes, &eval_args, &eval_named, Some(frame))
+                        .map_err(|e| e.at(self.span)),
+                    None => Err(match scopes.resolve_var(func_id) {
+                        Some(x) => RuntimeError::new(
+                            RuntimeErrorKind::TypeMismatch,
+                            format!("cannot call '{}' of type {}", func_id, x.type_name()),
+                        ),
+                        None => RuntimeError::new(
+                            RuntimeErrorKind::UndefinedFunction,
+                            format!("undefined function '{}'", func_id),
+                        ),
+                    }.at(self.span)),
                 }
             }
-            Expr::Id(ref x) => match scopes.resolve_var(x) {
 
-                // TODO: remove clone() requirement
-                Some(x) => x.clone(),
+            // A variable, or otherwise a Function or NativeFunction as a Value::Func
+            ExprKind::Id(x) => match scopes.resolve_var(x) {
+                Some(x) => Ok(x),
 
-                None => Value::None,
+                None => match scopes.resolve_callable(x) {
+                    Some(Callable::Func(f)) => Ok(Value::Func(Callable::Func(Function::bind(&f)))),
+                    Some(f) => Ok(Value::Func(f)),
+                    None => Err(undefined_var(x).at(self.span)),
+                },
             },
-            Expr::Int(x) => Value::Int(x),
-            Expr::List(ref exprs) => {
-                Value::List(
+            ExprKind::If(ref branches, ref else_expr) => {
+                for (cond, expr) in branches {
+                    if cond.eval(scopes)?.is_truthy() {
+                        return expr.eval(scopes);
+                    }
+                }
+                else_expr.eval(scopes)
+            }
+            ExprKind::Int(x) => Ok(Value::Int(x)),
+            ExprKind::List(ref exprs) => {
+                Ok(Value::List(
                     exprs
                         .iter()
                         .map(|x| x.eval(scopes))
-                        .collect::<Vec<Value<'src>>>()
-                )
+                        .collect::<Result<Vec<Value<'src>>, RuntimeError<'src>>>()?
+                ))
             }
-            Expr::ListElement(ref id, ref expr) => {
-                
-                // Match index: Value::Str for Dict index, Value::Int for List index
-                let coll_idx = expr.eval(scopes);
-                let var = scopes.resolve_var(id);
-
-                match var {
-                    Some(ref val) => match coll_idx {
-
-                        // Int index: val must be a List
-                        Value::Int(idx) => match val {
-                            Value::List(ref list) => match list.get(idx as usize) {
-                                Some(x) => x.clone(),
-                                None => Value::None,
-                            },
-                            _ => Value::None,
-                        },
-
-                        // Str index: val must be a Dict
-                        Value::Str(ref s) => match val {
-                            Value::Dict(ref dict) => match dict.get(s) {
-                                Some(x) => x.clone(),
-                                None => Value::None,
-                            },
-                            _ => Value::None,
-                        },
-
-                        _ => Value::None,
-                    }
-                    None => Value::None,
-                }
+            ExprKind::ListElement(..) => {
+                // When the chain of indexes begins with a variable (e.g. "a" in "a[1][2]"), its
+                // collection is borrowed rather than copied
+                let (root, path) = index_chain(self);
+                let res = match root.kind {
+                    ExprKind::Id(id) => {
+                        let path = path
+                            .iter()
+                            .map(|x| x.eval(scopes))
+                            .collect::<Result<Vec<Value<'src>>, RuntimeError<'src>>>()?;
+                        let config = scopes.config();
+                        scopes
+                            .with_var(id, |coll| resolve_path(config, coll, &path))
+                            .unwrap_or_else(|| Err(undefined_var(id)))
+                    },
+                    _ => {
+                        let coll = root.eval(scopes)?;
+                        let path = path
+                            .iter()
+                            .map(|x| x.eval(scopes))
+                            .collect::<Result<Vec<Value<'src>>, RuntimeError<'src>>>()?;
+                        resolve_path(scopes.config(), &coll, &path)
+                    },
+                };
+                res.map_err(|e| e.at(self.span))
             }
-            Expr::None    => Value::None,
-            Expr::Real(x) => Value::Real(x),
-            Expr::Str(x)  => Value::Str(x),
-            Expr::UnaryOp(ref opc, ref x) => opc.eval_unary(&x.eval(scopes)),
+            ExprKind::Lambda(ref params, rest, ref stmts) => {
+                let func = Function::new(params.clone(), rest, stmts.clone());
+                Ok(Value::Func(Callable::Func(Rc::new(func.with_env(scopes.capture(), None)))))
+            },
+            ExprKind::None    => Ok(Value::None),
+            ExprKind::Real(x) => Ok(Value::Real(x)),
+            ExprKind::Str(ref x) => Ok(Value::Str(x.clone())),
+            ExprKind::UnaryOp(ref opc, ref x) => {
+                let x = x.eval(scopes)?;
+                opc.eval_unary(scopes.config(), &x).map_err(|e| e.at(self.span))
+            },
         }
     }
 }
@@ -467,100 +1129,586 @@ impl<'src> Evaluatable<'src> for Expr<'src> {
 impl<'src> Executable<'src> for Stmt<'src> {
     /// Execute a Stmt
     fn exec(&self, scopes: &mut ScopeChain<'src>) -> ExecResult<'src> {
-        match *self {
+        match self.kind {
+            // Evaluate "expr" and update the existing variable "id" in whichever Scope it was
+            // declared, combining it with the variable's current Value first for a compound
+            // assignment. Value of the Assign is None.
+            StmtKind::Assign(id, ref opc, ref expr) => {
+                let eval_res = eval_or_return!(expr, scopes);
+                let cur = |scopes: &ScopeChain<'src>| scopes.resolve_var(id).ok_or_else(|| undefined_var(id));
+                let eval_res = match assigned_value(scopes, opc, cur, eval_res) {
+                    Ok(x) => x,
+                    Err(e) => return ExecResult::Error(e.at(self.span)),
+                };
+                if scopes.update_var(id, eval_res) {
+                    ExecResult::None
+                } else {
+                    ExecResult::Error(undefined_var(id).at(self.span))
+                }
+            }
+
+            // Execute a bare block of statements within its own Scope
+            StmtKind::Block(ref stmts) => exec_block(stmts, scopes, None),
+
             // Break from a loop
-            Stmt::Break => ExecResult::Break,
+            StmtKind::Break => ExecResult::Break,
+
+            // Skip to the next iteration of a loop
+            StmtKind::Continue => ExecResult::Continue,
 
             // Single Expr (e.g. function call)
-            Stmt::Expr(ref exp) => {
-                exp.eval(scopes);
+            StmtKind::Expr(ref exp) => {
+                eval_or_return!(exp, scopes);
                 ExecResult::None
             }
 
-            // Create a new Function in the Scope
-            Stmt::FnDef(ref fn_id, ref arg_ids, ref stmts) => {
-                scopes.insert_func(
-                    fn_id,
-                    Function {
-                        args:  arg_ids.clone(),
-                        stmts: stmts.clone(),
+            // Create a new Function in the Scope, which it holds weakly as its home (see
+            // Function). Functions declared within a StmtBlock have already been created when the
+            // StmtBlock was entered, see hoist().
+            StmtKind::FnDef(fn_id, ref params, rest, ref stmts) => {
+                let mut env = scopes.capture();
+                let home = env.pop().map(|x| Rc::downgrade(&x));
+                let func = Function::new(params.clone(), rest, stmts.clone());
+                scopes.insert_func(fn_id, func.with_env(env, home));
+                ExecResult::None
+            }
+
+            // Execute a loop once for each item of a List, or each key of a Dict (in sorted order),
+            // assigning the item to the variable "id"
+            StmtKind::ForIn(id, ref iter, ref stmts) => {
+                let items = match eval_or_return!(iter, scopes) {
+                    Value::List(items) => items,
+                    Value::Dict(dict) => {
+                        let mut keys = dict.into_keys().collect::<Vec<Cow<str>>>();
+                        keys.sort();
+                        keys.into_iter().map(Value::Str).collect()
+                    },
+                    x => {
+                        return ExecResult::Error(RuntimeError::new(
+                            RuntimeErrorKind::TypeMismatch,
+                            format!("cannot iterate over {}", x.type_name()),
+                        ).at(iter.span))
                     },
-                );
+                };
+                for item in items {
+                    if let Some(res) = exec_iteration(stmts, scopes, Some((id, item))) {
+                        return res;
+                    }
+                }
                 ExecResult::None
             }
 
-            // If condition without an else
-            Stmt::If(ref cond, ref stmts) => {
-                if let Value::Bool(b) = cond.eval(scopes) {
-                    if b {
-                        stmts.exec(scopes)
-                    } else {
-                        ExecResult::None
+            // Execute a loop once for each Int from "start" up to (but excluding) "end", assigning
+            // the Int to the variable "id"
+            StmtKind::ForRange(id, ref start, ref end, ref stmts) => {
+                let start = eval_or_return!(start, scopes);
+                let end   = eval_or_return!(end, scopes);
+                let (start, end) = match (start, end) {
+                    (Value::Int(start), Value::Int(end)) => (start, end),
+                    (start, end) => {
+                        return ExecResult::Error(RuntimeError::new(
+                            RuntimeErrorKind::TypeMismatch,
+                            format!(
+                                "cannot iterate over range of {} and {}",
+                                start.type_name(),
+                                end.type_name()
+                            ),
+                        ).at(self.span))
+                    },
+                };
+                for i in start..end {
+                    if let Some(res) = exec_iteration(stmts, scopes, Some((id, Value::Int(i)))) {
+                        return res;
                     }
-                } else {
-                    ExecResult::None
                 }
+                ExecResult::None
             }
 
-            // If condition with an else
-            Stmt::IfElse(ref cond, ref stmts, ref else_stmts) => {
-                if let Value::Bool(b) = cond.eval(scopes) {
-                    if b {
-                        stmts.exec(scopes)
-                    } else {
-                        else_stmts.exec(scopes)
+            // Execute the block of the first branch whose condition is truthy (see
+            // Value::is_truthy()), or the else block (if any) when no condition is
+            StmtKind::If(ref branches, ref else_stmts) => {
+                for (cond, stmts) in branches {
+                    if eval_or_return!(cond, scopes).is_truthy() {
+                        return exec_block(stmts, scopes, None);
                     }
-                } else {
-                    else_stmts.exec(scopes)
+                }
+                match *else_stmts {
+                    Some(ref stmts) => exec_block(stmts, scopes, None),
+                    None => ExecResult::None,
                 }
             }
 
             // Evaluate "expr" and update variable table (key: "id") with result. Value of the Let
             // is None.
-            Stmt::Let(ref id, ref expr) => {
-                let eval_res = expr.eval(scopes);
+            StmtKind::Let(id, ref expr) => {
+                let eval_res = eval_or_return!(expr, scopes);
                 scopes.insert_var(id, eval_res);
                 ExecResult::None
             }
 
-            // Assign a Value to a list item (integer index)
-            Stmt::ListItemAssignment(ref id, ref idx, ref val) => {
-                let idx = idx.eval(scopes);
-                let val = val.eval(scopes);
-                match idx {
-                    Value::Int(x) => scopes.insert_list_item(id, x as usize, val),
-                    Value::Str(x) => scopes.insert_dict_item(id, &x, val),
-                    _ => {},
+            // Assign a Value to a list item (integer index) or dict item (string index) reached
+            // through a chain of indexes, combining it with the item's current Value first for a
+            // compound assignment
+            StmtKind::ListItemAssignment(id, ref path, ref opc, ref val) => {
+                let path = match path
+                    .iter()
+                    .map(|x| x.eval(scopes))
+                    .collect::<Result<Vec<Value<'src>>, RuntimeError<'src>>>()
+                {
+                    Ok(x) => x,
+                    Err(e) => return ExecResult::Error(e),
+                };
+                let val = eval_or_return!(val, scopes);
+                let cur = |scopes: &ScopeChain<'src>| {
+                    scopes
+                        .with_var(id, |coll| resolve_path(scopes.config(), coll, &path))
+                        .unwrap_or_else(|| Err(undefined_var(id)))
                 };
+                let val = match assigned_value(scopes, opc, cur, val) {
+                    Ok(x) => x,
+                    Err(e) => return ExecResult::Error(e.at(self.span)),
+                };
+                let stored = scopes
+                    .with_var_mut(id, |coll| store_path(coll, &path, val))
+                    .unwrap_or_else(|| Err(undefined_var(id)));
+
+                // An assignment that cannot be made is ignored unless in strict mode, but a List
+                // that cannot be extended is always an error
+                if let Err(e) = stored {
+                    if e.kind == RuntimeErrorKind::Overflow {
+                        return ExecResult::Error(e.at(self.span));
+                    }
+                    if let Err(e) = scopes.config().fallback(|| e) {
+                        return ExecResult::Error(e.at(self.span));
+                    }
+                }
                 ExecResult::None
             }
 
-            // Execute a loop until the result of executing a loop Stmt is ExecResult::Break
-            Stmt::Loop(ref stmts) => loop {
-                let res = stmts.exec(scopes);
-                if let ExecResult::Break = res {
-                    return ExecResult::None;
+            // Execute a loop until the result of executing a loop Stmt is ExecResult::Break, or
+            // until a Return or Error needs to be propagated
+            StmtKind::Loop(ref stmts) => loop {
+                if let Some(res) = exec_iteration(stmts, scopes, None) {
+                    return res;
                 }
             },
 
             // Return from a Function
-            Stmt::Return(ref expr) => ExecResult::Return(expr.eval(scopes)),
+            StmtKind::Return(ref expr) => ExecResult::Return(eval_or_return!(expr, scopes)),
+
+            // Execute a loop for as long as "cond" evaluates to a truthy Value
+            StmtKind::While(ref cond, ref stmts) => {
+                while eval_or_return!(cond, scopes).is_truthy() {
+                    if let Some(res) = exec_iteration(stmts, scopes, None) {
+                        return res;
+                    }
+                }
+                ExecResult::None
+            }
         }
     }
 }
 
 impl<'src> Executable<'src> for StmtBlock<'src> {
-    /// Execute StmtBlock: execute all Stmts in turn, stopping prematurely if an ExecResult::Break
-    /// or ExecResult::Return is encountered.
+    /// Execute StmtBlock: create all Functions declared by the StmtBlock (see hoist()), then
+    /// execute all other Stmts in turn, stopping prematurely if an ExecResult::Break,
+    /// ExecResult::Continue, ExecResult::Return or ExecResult::Error is encountered.
     fn exec(&self, scopes: &mut ScopeChain<'src>) -> ExecResult<'src> {
+        if let Err(e) = hoist(self, scopes) {
+            return ExecResult::Error(e);
+        }
         for stmt in self {
+            if let StmtKind::FnDef(..) = stmt.kind {
+                continue;
+            }
             let res = stmt.exec(scopes);
             match res {
-                ExecResult::Return(_) => { return res; },
+                ExecResult::Return(_) | ExecResult::Error(_) => { return res; },
                 ExecResult::Break     => { return ExecResult::Break },
+                ExecResult::Continue  => { return ExecResult::Continue },
                 _ => {},
             }
         }
         ExecResult::None
     }
 }
+
+/// Creates each Function declared by the StmtBlock `stmts` in the last Scope before any Stmt is
+/// executed, so that a Function can be called before its declaration (e.g. by a mutually
+/// recursive Function)
+///
+/// A Function may only be declared once within a StmtBlock. Nothing is created if it is declared
+/// more than once.
+fn hoist<'src>(
+    stmts: &StmtBlock<'src>,
+    scopes: &mut ScopeChain<'src>,
+) -> Result<(), RuntimeError<'src>> {
+    let is_def = |stmt: &Stmt<'src>, id: Ident<'src>| match stmt.kind {
+        StmtKind::FnDef(x, ..) => x == id,
+        _ => false,
+    };
+    for (i, stmt) in stmts.iter().enumerate() {
+        if let StmtKind::FnDef(id, ..) = stmt.kind {
+            if stmts[..i].iter().any(|x| is_def(x, id)) {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::DuplicateDefinition,
+                    format!("function '{}' is already defined in this block", id),
+                ).at(stmt.span));
+            }
+        }
+    }
+    for stmt in stmts {
+        if let StmtKind::FnDef(..) = stmt.kind {
+            stmt.exec(scopes);
+        }
+    }
+    Ok(())
+}
+
+/// Executes a StmtBlock within a new Scope, which is removed (along with any variables and
+/// Functions declared by the StmtBlock) afterwards
+///
+/// The Scope initially contains the variable `var` (e.g. a for loop's variable), if any. To keep
+/// the cost of blocks low, no Scope is created when there is no such variable and the StmtBlock
+/// itself declares no variables or Functions.
+fn exec_block<'src>(
+    stmts: &StmtBlock<'src>,
+    scopes: &mut ScopeChain<'src>,
+    var: Option<(Ident<'src>, Value<'src>)>,
+) -> ExecResult<'src> {
+    let declares = stmts
+        .iter()
+        .any(|x| matches!(x.kind, StmtKind::FnDef(..) | StmtKind::Let(..)));
+    if var.is_none() && !declares {
+        return stmts.exec(scopes);
+    }
+
+    let mut scope = Scope::new();
+    if let Some((id, val)) = var {
+        scope.vars.insert(id, val);
+    }
+    scopes.push(scope);
+    let res = stmts.exec(scopes);
+    scopes.pop_release();
+    res
+}
+
+/// Executes the StmtBlock of a single loop iteration within its own Scope, see exec_block()
+///
+/// Returns the ExecResult of the whole loop if the loop must stop: ExecResult::None after a
+/// Break, or a Return or Error to be propagated.
+fn exec_iteration<'src>(
+    stmts: &StmtBlock<'src>,
+    scopes: &mut ScopeChain<'src>,
+    var: Option<(Ident<'src>, Value<'src>)>,
+) -> Option<ExecResult<'src>> {
+    match exec_block(stmts, scopes, var) {
+        ExecResult::Break => Some(ExecResult::None),
+        res @ ExecResult::Return(_) | res @ ExecResult::Error(_) => Some(res),
+        _ => None,
+    }
+}
+
+/// Evaluates a BinOp Expr along with the chain of BinOps nested as its left-hand operands (e.g.
+/// "1 + 2 - 3" is "(1 + 2) - 3"), from the innermost outwards
+///
+/// The chain is evaluated iteratively rather than recursively, so that long chains of operators do
+/// not exhaust the stack.
+///
+/// The logical operators && and || short-circuit: the right-hand operand is only evaluated when
+/// the left-hand operand does not determine the result.
+#[inline(never)]
+fn eval_binops<'src>(
+    expr: &Expr<'src>,
+    scopes: &mut ScopeChain<'src>,
+) -> Result<Value<'src>, RuntimeError<'src>> {
+    let mut chain = vec![];
+    let mut first = expr;
+    while let ExprKind::BinOp(ref l, ref opc, ref r) = first.kind {
+        chain.push((first.span, opc, r));
+        first = l;
+    }
+    let mut res = first.eval(scopes)?;
+    for (span, opc, r) in chain.into_iter().rev() {
+        res = match *opc {
+            Opcode::LogicalAnd => Value::Bool(res.is_truthy() && r.eval(scopes)?.is_truthy()),
+            Opcode::LogicalOr  => Value::Bool(res.is_truthy() || r.eval(scopes)?.is_truthy()),
+            _ => {
+                let r = r.eval(scopes)?;
+                opc.eval(scopes.config(), res, r).map_err(|e| e.at(span))?
+            },
+        };
+    }
+    Ok(res)
+}
+
+/// Evaluates the named argument Exprs of a call in turn
+fn eval_named<'src>(
+    named: &[(Ident<'src>, Box<Expr<'src>>)],
+    scopes: &mut ScopeChain<'src>,
+) -> Result<Vec<(Ident<'src>, Value<'src>)>, RuntimeError<'src>> {
+    named
+        .iter()
+        .map(|(id, x)| Ok((*id, x.eval(scopes)?)))
+        .collect()
+}
+
+/// Returns the Value to be stored by an assignment
+///
+/// For a compound assignment, this is the result of applying `opc` to the target's current Value
+/// (returned by `cur`) and `val`. Otherwise it is `val` itself.
+fn assigned_value<'src, F>(
+    scopes: &ScopeChain<'src>,
+    opc: &Option<Opcode>,
+    cur: F,
+    val: Value<'src>,
+) -> Result<Value<'src>, RuntimeError<'src>>
+where
+    F: FnOnce(&ScopeChain<'src>) -> Result<Value<'src>, RuntimeError<'src>>,
+{
+    match *opc {
+        Some(ref opc) => opc.eval(scopes.config(), cur(scopes)?, val),
+        None => Ok(val),
+    }
+}
+
+/// Returns the text of a Str, Int or Real Value for string concatenation
+fn concat_text(x: &Value) -> Option<String> {
+    match x {
+        Value::Int(x)  => Some(format!("{}", x)),
+        Value::Real(x) => Some(format!("{}", x)),
+        Value::Str(x)  => Some(x.to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the text `s` repeated `n` times, or an empty String if `n` is negative
+///
+/// A RuntimeError is returned if the result would be too long to be stored, rather than aborting
+/// the interpreter.
+fn repeat_text<'src>(s: &str, n: i64) -> Result<String, RuntimeError<'src>> {
+    let count = usize::try_from(cmp::max(n, 0)).ok();
+    let mut res = String::new();
+    match count.and_then(|x| s.len().checked_mul(x)) {
+        Some(0) => Ok(res),
+        Some(len) if res.try_reserve_exact(len).is_ok() => {
+            for _ in 0..n {
+                res.push_str(s);
+            }
+            Ok(res)
+        },
+        _ => Err(RuntimeError::new(
+            RuntimeErrorKind::Overflow,
+            format!("cannot repeat a Str of {} bytes {} times", s.len(), n),
+        )),
+    }
+}
+
+/// Creates a RuntimeError for a collection `coll` that cannot be indexed with `idx`
+fn invalid_index<'src>(coll: &Value<'src>, idx: &Value<'src>) -> RuntimeError<'src> {
+    RuntimeError::new(
+        RuntimeErrorKind::TypeMismatch,
+        format!("cannot index {} with {}", coll.type_name(), idx.type_name()),
+    )
+}
+
+/// Splits a chain of ListElements (e.g. "a[1][2]") into its root collection Expr ("a") and its
+/// index Exprs in order ([1, 2])
+fn index_chain<'a, 'src>(mut expr: &'a Expr<'src>) -> (&'a Expr<'src>, Vec<&'a Expr<'src>>) {
+    let mut path = vec![];
+    while let ExprKind::ListElement(ref coll, ref idx) = expr.kind {
+        path.push(&**idx);
+        expr = coll;
+    }
+    path.reverse();
+    (expr, path)
+}
+
+/// Returns a reference to the item at index `idx` of the collection `coll`
+///
+/// An Int index must be used with a List and a Str index with a Dict.
+fn item<'a, 'src>(
+    coll: &'a Value<'src>,
+    idx: &Value<'src>,
+) -> Result<&'a Value<'src>, RuntimeError<'src>> {
+    match (coll, idx) {
+        (Value::List(list), Value::Int(i)) => {
+            usize::try_from(*i).ok().and_then(|x| list.get(x)).ok_or_else(|| out_of_range(*i))
+        },
+        (Value::Dict(dict), Value::Str(s)) => dict.get(s).ok_or_else(|| missing_key(s)),
+        (coll, idx) => Err(invalid_index(coll, idx)),
+    }
+}
+
+/// Returns a mutable reference to the item at index `idx` of the collection `coll`, see item()
+fn item_mut<'a, 'src>(
+    coll: &'a mut Value<'src>,
+    idx: &Value<'src>,
+) -> Result<&'a mut Value<'src>, RuntimeError<'src>> {
+    match (coll, idx) {
+        (Value::List(list), Value::Int(i)) => {
+            usize::try_from(*i).ok().and_then(move |x| list.get_mut(x)).ok_or_else(|| out_of_range(*i))
+        },
+        (Value::Dict(dict), Value::Str(s)) => dict.get_mut(s).ok_or_else(|| missing_key(s)),
+        (coll, idx) => Err(invalid_index(coll, idx)),
+    }
+}
+
+/// Returns the item reached from the collection `coll` through the chain of indexes `path`
+///
+/// Missing items and invalid index types produce Value::None unless in strict mode.
+fn resolve_path<'src>(
+    config: &Config,
+    coll: &Value<'src>,
+    path: &[Value<'src>],
+) -> Result<Value<'src>, RuntimeError<'src>> {
+    let mut cur = coll;
+    for idx in path {
+        cur = match item(cur, idx) {
+            Ok(x) => x,
+            Err(e) => return config.fallback(|| e),
+        };
+    }
+    Ok(cur.clone())
+}
+
+/// Stores `val` as the item reached from the collection `coll` through the chain of indexes `path`
+///
+/// Only the final index may refer to a new item: a new key of a Dict, or an index beyond the end
+/// of a List, which is extended with Value::None items as required. An Overflow RuntimeError is
+/// returned if the List cannot be extended that far.
+fn store_path<'src>(
+    coll: &mut Value<'src>,
+    path: &[Value<'src>],
+    val: Value<'src>,
+) -> Result<(), RuntimeError<'src>> {
+    let (last, path) = match path.split_last() {
+        Some(x) => x,
+        None => {
+            *coll = val;
+            return Ok(());
+        },
+    };
+    let mut cur = coll;
+    for idx in path {
+        cur = item_mut(cur, idx)?;
+    }
+    match (cur, last) {
+        (Value::List(list), &Value::Int(i)) if i >= 0 => {
+            let extra = usize::try_from(i).ok().and_then(|x| (x + 1).checked_sub(list.len()));
+            if let Some(extra) = extra.filter(|x| *x > 0) {
+                if list.try_reserve_exact(extra).is_err() {
+                    return Err(RuntimeError::new(
+                        RuntimeErrorKind::Overflow,
+                        format!("cannot extend a List of {} items to index {}", list.len(), i),
+                    ));
+                }
+                list.resize(list.len() + extra, Value::None);
+            }
+            list[i as usize] = val;
+        },
+        (Value::List(_), &Value::Int(i)) => return Err(out_of_range(i)),
+        (Value::Dict(dict), Value::Str(s)) => {
+            dict.insert(s.clone(), val);
+        },
+        (cur, last) => return Err(invalid_index(cur, last)),
+    }
+    Ok(())
+}
+
+/// Breaks the reference cycles between a Scope that has left the ScopeChain and the Functions
+/// that were created within it
+///
+/// Such a Function captures the Scope, so storing it in the Scope (e.g. within a List held by a
+/// variable) keeps both alive. Functions stored directly in the Scope only hold it weakly instead,
+/// see Function. If the Scope and those Functions are not referred to from anywhere else,
+/// the Scope is cleared so that both can be freed. Otherwise (e.g. a Function was returned from
+/// the Function call that created the Scope), the Scope is left as it is.
+fn release(scope: Rc<RefCell<Scope>>) {
+    {
+        let inner = scope.borrow();
+        let funcs = inner
+            .funcs
+            .values()
+            .chain(inner.vars.values().filter_map(|x| match x {
+                Value::Func(Callable::Func(f)) => Some(f),
+                _ => None,
+            }))
+            .filter(|f| f.env.iter().any(|x| Rc::ptr_eq(x, &scope)))
+            .collect::<Vec<&Rc<Function>>>();
+        if funcs.is_empty() {
+            return;
+        }
+
+        // Each distinct Function holds one reference to the Scope, and may only be referred to
+        // from within the Scope itself
+        let mut distinct = 0;
+        for (i, f) in funcs.iter().enumerate() {
+            if funcs[..i].iter().any(|x| Rc::ptr_eq(x, f)) {
+                continue;
+            }
+            if Rc::strong_count(f) != funcs.iter().filter(|x| Rc::ptr_eq(x, f)).count() {
+                return;
+            }
+            distinct += 1;
+        }
+        if Rc::strong_count(&scope) != distinct + 1 {
+            return;
+        }
+    }
+    let mut inner = scope.borrow_mut();
+    inner.funcs.clear();
+    inner.vars.clear();
+}
+
+/// Returns a RuntimeError if `n` arguments are not accepted by `arity`
+fn check_arity<'src>(arity: Arity, n: usize) -> Result<(), RuntimeError<'src>> {
+    if arity.accepts(n) {
+        return Ok(());
+    }
+    Err(RuntimeError::new(
+        RuntimeErrorKind::ArityMismatch,
+        format!("expected {}, found {}", arity, n),
+    ))
+}
+
+/// Creates a RuntimeError for a named argument `id` that matches a parameter which already has an
+/// argument
+fn repeated_arg<'src>(id: &str) -> RuntimeError<'src> {
+    RuntimeError::new(
+        RuntimeErrorKind::InvalidArgument,
+        format!("argument '{}' passed more than once", id),
+    )
+}
+
+/// Creates a RuntimeError for a named argument `id` that does not match any parameter
+fn unknown_arg<'src>(id: &str) -> RuntimeError<'src> {
+    RuntimeError::new(
+        RuntimeErrorKind::InvalidArgument,
+        format!("unknown argument '{}'", id),
+    )
+}
+
+/// Creates a RuntimeError for a key `key` that is not present in a Dict
+fn missing_key<'src>(key: &str) -> RuntimeError<'src> {
+    RuntimeError::new(
+        RuntimeErrorKind::InvalidIndex,
+        format!("key \"{}\" not found in dict", key),
+    )
+}
+
+/// Creates a RuntimeError for an index `idx` that is out of range for a List
+fn out_of_range<'src>(idx: i64) -> RuntimeError<'src> {
+    RuntimeError::new(
+        RuntimeErrorKind::InvalidIndex,
+        format!("index {} out of range for list", idx),
+    )
+}
+
+/// Creates a RuntimeError for a reference to the undefined variable `id`
+fn undefined_var<'src>(id: Ident<'src>) -> RuntimeError<'src> {
+    RuntimeError::new(
+        RuntimeErrorKind::UndefinedVariable,
+        format!("undefined variable '{}'", id),
+    )
+}
@@ -13,7 +13,7 @@ mod parser;
 pub mod runtime;
 
 use ast::{ExecResult, Executable};
-use interpreter::{Scope, ScopeChain};
+use interpreter::{Config, Scope, ScopeChain};
 use parser::parse;
 use runtime::insert_native_functions;
 
@@ -41,10 +41,27 @@ pub fn get_default_global_scope<'src>() -> Scope<'src> {
 ///   - `global_scope: Scope`: root scope under which to execute the code
 ///
 pub fn interpret<'src>(src: &'src str, global_scope: Scope<'src>) -> InterpretResult<'src> {
+    interpret_with_config(src, global_scope, Config::default())
+}
+
+/// Interprets given source code under a Scope using an interpreter Config
+///
+/// # Params
+///
+///   - `src: &str`: source code to parse and execute
+///   - `global_scope: Scope`: root scope under which to execute the code
+///   - `config: Config`: interpreter configuration (e.g. strict mode)
+///
+pub fn interpret_with_config<'src>(
+    src: &'src str,
+    global_scope: Scope<'src>,
+    config: Config,
+) -> InterpretResult<'src> {
     let mut scopes = ScopeChain::from_scope(global_scope);
+    scopes.set_config(config);
     let er = match parse(src) {
         Ok(stmts) => stmts.exec(&mut scopes),
-        Err(s)    => ExecResult::Error(s),
+        Err(e)    => ExecResult::ParseError(e),
     };
     InterpretResult {
         exec_result: er,
@@ -55,14 +72,18 @@ pub fn interpret<'src>(src: &'src str, global_scope: Scope<'src>) -> InterpretRe
 #[cfg(all(test, not(feature = "no_std")))]
 mod tests {
     use std::any::Any;
+    use std::borrow::Cow;
     use std::cell::RefCell;
     use std::collections::HashMap;
     use std::rc::Rc;
 
     use super::*;
+    use ast::StripSpans;
 
-    use ast::{Executable, Expr, Ident, Opcode, NativeFunction, Stmt, Value};
-    use interpreter::{Scope, ScopeChain};
+    use ast::{
+        Arity, Callable, Executable, ExprKind, Ident, Opcode, NativeFunction, RuntimeErrorKind, StmtKind, Value,
+    };
+    use interpreter::{Arithmetic, Scope, ScopeChain};
     use parser::parse;
 
     struct TestPrint {
@@ -70,7 +91,7 @@ mod tests {
     }
     impl TestPrint {
         pub fn get_calls(&self) -> usize {
-            self.calls.borrow().clone()
+            *self.calls.borrow()
         }
         pub fn assert_calls(&self, num: usize) {
             assert_eq!(num, self.get_calls());
@@ -90,7 +111,7 @@ mod tests {
     }
     impl TestPrintLn {
         pub fn get_calls(&self) -> usize {
-            self.calls.borrow().clone()
+            *self.calls.borrow()
         }
         pub fn assert_calls(&self, num: usize) {
             assert_eq!(num, self.get_calls());
@@ -117,26 +138,33 @@ mod tests {
         scope.native_funcs.insert("println", test_println);
     }
 
+    fn error_kind(res: ExecResult) -> Option<RuntimeErrorKind> {
+        match res {
+            ExecResult::Error(e) => Some(e.kind),
+            _ => None,
+        }
+    }
+
     #[test]
     fn let_stmt() {
 
         // Test parsing
         assert_eq!(
             Ok(vec![
-                Stmt::Let(
+                StmtKind::Let(
                     "a",
-                    Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Add, Box::new(Expr::Int(2)))
-                )
+                    ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Add, Box::new(ExprKind::Int(2).into())).into()
+                ).into()
             ]),
-            parse("let a = 1 + 2;")
+            parse("let a = 1 + 2;").strip_spans()
         );
 
         let mut scopes = ScopeChain::from_scope(Scope::new());
 
         // Test evaluation of expression using an undefined variable
         assert_eq!(
-            ExecResult::Return(Value::None),
-            parse("return a + 1").unwrap().exec(&mut scopes)
+            Some(RuntimeErrorKind::UndefinedVariable),
+            error_kind(parse("return a + 1").unwrap().exec(&mut scopes))
         );
 
         // Test evaluation of a Let statement
@@ -145,7 +173,7 @@ mod tests {
             ExecResult::Return(Value::Int(3)),
             parse("let a = 1 + 2; return a;").unwrap().exec(&mut scopes)
         );
-        assert_eq!(Some(&Value::Int(3)), scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Int(3)), scopes.resolve_var("a"));
 
         // Test evaluation of expressions using variable "a" (now defined in "scope")
         assert_eq!(
@@ -205,36 +233,42 @@ mod tests {
 
         // Strings
         assert_eq!(
-            ExecResult::Return(Value::Str("Hello")),
+            ExecResult::Return(Value::Str("Hello".into())),
             interpret(r#"return "Hello";"#, Scope::new()).exec_result
         );
         assert_eq!(
-            ExecResult::Return(Value::Str("Hello world!")),
+            ExecResult::Return(Value::Str("Hello world!".into())),
             interpret(r#"return "Hello world!";"#, Scope::new()).exec_result
         );
         assert_eq!(
-            ExecResult::Return(Value::Str("Hello'world!")),
+            ExecResult::Return(Value::Str("Hello'world!".into())),
             interpret(r#"return "Hello'world!";"#, Scope::new()).exec_result
         );
-        // TODO: escaped " in Strings
-        //assert_eq!("Str(\"Hello\"world!\")", format!("{:?}", ExprParser::new().parse(r#""Hello\"world!""#).unwrap()));
+        assert_eq!(
+            ExecResult::Return(Value::Str("Hello\"world!\n".into())),
+            interpret(r#"return "Hello\"world!\n";"#, Scope::new()).exec_result
+        );
+        assert_eq!(
+            ExecResult::Return(Value::Str("caf\u{e9}".into())),
+            interpret(r#"return "caf\u{e9}";"#, Scope::new()).exec_result
+        );
 
         // Ids
         assert_eq!(
-            Ok(vec![Stmt::Expr(Expr::Id("a"))]),
-            parse("a")
+            Ok(vec![StmtKind::Expr(ExprKind::Id("a").into()).into()]),
+            parse("a").strip_spans()
         );
         assert_eq!(
-            Ok(vec![Stmt::Expr(Expr::Id("_a"))]),
-            parse("_a")
+            Ok(vec![StmtKind::Expr(ExprKind::Id("_a").into()).into()]),
+            parse("_a").strip_spans()
         );
         assert_eq!(
-            Ok(vec![Stmt::Expr(Expr::Id("a123"))]),
-            parse("a123")
+            Ok(vec![StmtKind::Expr(ExprKind::Id("a123").into()).into()]),
+            parse("a123").strip_spans()
         );
         assert_eq!(
-            Ok(vec![Stmt::Expr(Expr::Id("a123_45"))]),
-            parse("a123_45")
+            Ok(vec![StmtKind::Expr(ExprKind::Id("a123_45").into()).into()]),
+            parse("a123_45").strip_spans()
         );
     }
 
@@ -243,15 +277,165 @@ mod tests {
         // Test language expression precedence
         // 1 + (2 * 3 / 4) + 42 = 1 + 1.5 + 42 = Real(44.5)
         let scopes = interpret("fn test(b) { return b; }; let a = 1 + 2 * 3 / 4 + test(42);", Scope::new()).scope_chain;
-        assert_eq!(Some(&Value::Real(44.5)), scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Real(44.5)), scopes.resolve_var("a"));
+
+        // Binary operators are left-associative
+        assert_eq!(ExecResult::Return(Value::Int(-4)),   interpret("return 1 - 2 - 3;",  Scope::new()).exec_result);
+        assert_eq!(ExecResult::Return(Value::Real(1.0)), interpret("return 10 / 2 / 5;", Scope::new()).exec_result);
+        assert_eq!(ExecResult::Return(Value::Int(2)),    interpret("return 2 * 3 % 4;",  Scope::new()).exec_result);
+
+        // Relational operators bind more loosely than arithmetic ones
+        assert_eq!(ExecResult::Return(Value::Bool(true)),  interpret("return 1 + 2 < 4;",     Scope::new()).exec_result);
+        assert_eq!(ExecResult::Return(Value::Bool(false)), interpret("return 2 * 3 == 5 + 2;", Scope::new()).exec_result);
+
+        // Logical operators bind more loosely than relational ones
+        assert_eq!(ExecResult::Return(Value::Bool(true)), interpret("return 1 < 2 && 3 > 2;", Scope::new()).exec_result);
+
+        // && binds more tightly than ^, which binds more tightly than ||
+        assert_eq!(ExecResult::Return(Value::Bool(true)),  interpret("return true || false && false;", Scope::new()).exec_result);
+        assert_eq!(ExecResult::Return(Value::Bool(false)), interpret("return true ^ true && true;",    Scope::new()).exec_result);
+        assert_eq!(ExecResult::Return(Value::Bool(true)),  interpret("return true || true ^ true;",    Scope::new()).exec_result);
+    }
+
+    #[test]
+    fn long_expressions() {
+        // Long chains of binary operators are parsed and evaluated without exhausting the stack
+        let src = format!("return {};", vec!["1"; 1000].join(" + "));
+        assert_eq!(ExecResult::Return(Value::Int(1000)), interpret(&src, Scope::new()).exec_result);
+        let src = format!("return {};", vec!["true"; 1000].join(" && "));
+        assert_eq!(ExecResult::Return(Value::Bool(true)), interpret(&src, Scope::new()).exec_result);
     }
 
     #[test]
     fn scope_inheritance() {
         let scopes = interpret("let a = 1; fn test(z) { return a + z; }; let b = test(2); let c = a;", Scope::new()).scope_chain;
-        assert_eq!(Some(&Value::Int(1)), scopes.resolve_var("a"));
-        assert_eq!(Some(&Value::Int(3)), scopes.resolve_var("b"));
-        assert_eq!(Some(&Value::Int(1)), scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Int(1)), scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Int(3)), scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Int(1)), scopes.resolve_var("c"));
+    }
+
+    #[test]
+    fn block_scopes() {
+        // Variables and Functions declared within blocks are discarded at the end of the block
+        let scopes = interpret(
+            r#"
+            let a = 1;
+            if true { let a = 2; let b = 3; fn f() { return 4; }; };
+            { let c = a; a = 5; };
+            let d = 0;
+            for i in 0..3 { let t = i * 2; d += t; };
+            loop { let t = d; d = t + 1; if d > 7 { break; }; };
+            "#,
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(5)), scopes.resolve_var("a"));
+        assert_eq!(None,                 scopes.resolve_var("b"));
+        assert_eq!(None,                 scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Int(8)), scopes.resolve_var("d"));
+        assert_eq!(None,                 scopes.resolve_var("t"));
+        assert!(scopes.resolve_func("f").is_none());
+
+        // Inner blocks can read variables of enclosing blocks
+        assert_eq!(
+            ExecResult::Return(Value::Int(3)),
+            interpret("{ let a = 1; { let b = a + 2; return b; }; };", Scope::new()).exec_result
+        );
+
+        // Block scopes are removed even when a block is left early
+        let scopes = interpret(
+            "fn f() { while true { let x = 1; return x; }; }; let r = f(); let x = null;",
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(1)), scopes.resolve_var("r"));
+        assert_eq!(Some(Value::None),   scopes.resolve_var("x"));
+        assert_eq!(
+            Some(RuntimeErrorKind::UndefinedVariable),
+            error_kind(interpret("if true { let x = 1; }; return x;", Scope::new()).exec_result)
+        );
+    }
+
+    #[test]
+    fn assignment() {
+        // Assignment updates a variable in the Scope in which it was declared, whereas Let shadows
+        // it within a Function's Scope
+        let scopes = interpret(
+            r#"
+            let count = 0;
+            let shadowed = 0;
+            fn incr(x) { count = count + x; let shadowed = 10; return count; };
+            incr(1);
+            let res = incr(2);
+            "#,
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(3)), scopes.resolve_var("count"));
+        assert_eq!(Some(Value::Int(0)), scopes.resolve_var("shadowed"));
+        assert_eq!(Some(Value::Int(3)), scopes.resolve_var("res"));
+
+        // Assignment to a Function argument only updates the Function's Scope
+        let scopes = interpret(
+            "let x = 1; fn f(x) { x = x + 1; return x; }; let y = f(5);",
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(1)), scopes.resolve_var("x"));
+        assert_eq!(Some(Value::Int(6)), scopes.resolve_var("y"));
+
+        // Assignment to an undeclared variable is an error
+        let res = interpret("let a = 1;
+b = a;", Scope::new());
+        match res.exec_result {
+            ExecResult::Error(e) => {
+                assert_eq!(RuntimeErrorKind::UndefinedVariable, e.kind);
+                assert_eq!((2, 1), (e.span.line, e.span.column));
+            },
+            x => panic!("interpret() should have returned Error, not {:?}", x),
+        };
+        assert_eq!(None, res.scope_chain.resolve_var("b"));
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let scopes = interpret(
+            r#"
+            let a = 10; a += 5; a -= 3; a *= 4; a %= 5; a /= 2;
+            let s = "ab"; s += "c"; s *= 2;
+            let l = [1, 2, 3]; l[1] += 10; l[2] *= l[2];
+            let d = {"x": 1}; d["x"] -= 3;
+            "#,
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Real(1.5f64)),         scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Str("abcabc".into())), scopes.resolve_var("s"));
+        assert_eq!(
+            Some(Value::List(vec![Value::Int(1), Value::Int(12), Value::Int(9)])),
+            scopes.resolve_var("l")
+        );
+        match scopes.resolve_var("d") {
+            Some(Value::Dict(d)) => assert_eq!(Some(&Value::Int(-2)), d.get("x")),
+            x => panic!("d should be a Dict, not {:?}", x),
+        };
+
+        // The index of a compound assignment is evaluated only once
+        let scopes = interpret(
+            "let calls = 0; fn idx() { calls += 1; return 0; }; let l = [5]; l[idx()] += 1;",
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(1)),                    scopes.resolve_var("calls"));
+        assert_eq!(Some(Value::List(vec![Value::Int(6)])), scopes.resolve_var("l"));
+
+        // Compound assignment requires an existing target of a suitable type
+        assert_eq!(
+            Some(RuntimeErrorKind::UndefinedVariable),
+            error_kind(interpret("b += 1;", Scope::new()).exec_result)
+        );
+        assert_eq!(
+            Some(RuntimeErrorKind::TypeMismatch),
+            error_kind(interpret("let b = [1]; b -= 1;", Scope::new()).exec_result)
+        );
+        assert_eq!(
+            Some(RuntimeErrorKind::TypeMismatch),
+            error_kind(interpret("let b = [1]; b[3] += 1;", Scope::new()).exec_result)
+        );
     }
 
     #[test]
@@ -260,10 +444,12 @@ mod tests {
         insert_test_functions(&mut scope);
         let res = interpret("return 42", scope);
         match res.exec_result {
-            ExecResult::None => assert!(false, "interpret() should not have returned None"),
-            ExecResult::Break => assert!(false, "interpret() should not have returned Break"),
+            ExecResult::None => panic!("interpret() should not have returned None"),
+            ExecResult::Break => panic!("interpret() should not have returned Break"),
+            ExecResult::Continue => panic!("interpret() should not have returned Continue"),
             ExecResult::Return(x) => assert_eq!(Value::Int(42), x),
-            ExecResult::Error(e) => assert!(false, e),
+            ExecResult::Error(e) => panic!("{}", e),
+            ExecResult::ParseError(e) => panic!("{}", e),
         };
         res.scope_chain
             .resolve_native_func("print")
@@ -289,11 +475,11 @@ mod tests {
                 let res   = 1;
                 loop {
                     let temp = res;
-                    let res = res + prev;
-                    let prev = temp;
+                    res = res + prev;
+                    prev = temp;
                     print(res);
                     print(", ");
-                    let count = count - 1;
+                    count = count - 1;
                     if count <= 1 {
                         break;
                     };
@@ -308,10 +494,12 @@ mod tests {
         insert_test_functions(&mut scope);
         let res = interpret(src, scope);
         match res.exec_result {
-            ExecResult::None => assert!(false, "interpret() should not have returned None"),
-            ExecResult::Break => assert!(false, "interpret() should not have returned Break"),
+            ExecResult::None => panic!("interpret() should not have returned None"),
+            ExecResult::Break => panic!("interpret() should not have returned Break"),
+            ExecResult::Continue => panic!("interpret() should not have returned Continue"),
             ExecResult::Return(x) => assert_eq!(Value::Int(21), x),
-            ExecResult::Error(e) => assert!(false, e),
+            ExecResult::Error(e) => panic!("{}", e),
+            ExecResult::ParseError(e) => panic!("{}", e),
         };
 
         // print should have been invoked twice per loop (=14)
@@ -350,10 +538,12 @@ mod tests {
         insert_test_functions(&mut scope);
         let res = interpret(src, scope);
         match res.exec_result {
-            ExecResult::None => assert!(false, "interpret() should not have returned None"),
-            ExecResult::Break => assert!(false, "interpret() should not have returned Break"),
+            ExecResult::None => panic!("interpret() should not have returned None"),
+            ExecResult::Break => panic!("interpret() should not have returned Break"),
+            ExecResult::Continue => panic!("interpret() should not have returned Continue"),
             ExecResult::Return(x) => assert_eq!(Value::Int(24), x),
-            ExecResult::Error(e) => assert!(false, e),
+            ExecResult::Error(e) => panic!("{}", e),
+            ExecResult::ParseError(e) => panic!("{}", e),
         };
         res.scope_chain
             .resolve_native_func("print")
@@ -371,6 +561,172 @@ mod tests {
             .assert_calls(0);
     }
 
+    #[test]
+    fn parse_errors() {
+        let res = interpret("let a = 1;\nlet b = (a + 2;", Scope::new());
+        match res.exec_result {
+            ExecResult::ParseError(e) => {
+                assert_eq!((2, 15), (e.line, e.column));
+                assert_eq!("')'", e.expected);
+            },
+            x => panic!("interpret() should have returned ParseError, not {:?}", x),
+        };
+
+        // Nothing should have been executed
+        assert_eq!(None, res.scope_chain.resolve_var("a"));
+
+        // Integer literals must fit in an i64
+        match interpret("let a = 1;\nlet b = [0x8000_0000_0000_0000];", Scope::new()).exec_result {
+            ExecResult::ParseError(e) => {
+                assert_eq!((2, 10), (e.line, e.column));
+                assert_eq!("0x8000_0000_0000_0000];", e.snippet);
+            },
+            x => panic!("interpret() should have returned ParseError, not {:?}", x),
+        };
+
+        // "break" and "continue" must be within a loop of the same Function
+        for (src, col) in [
+            ("fn f() { continue; return 1; }; f();",         10),
+            ("break;",                                       1),
+            ("if true { let a = 1; } else { continue; };",   31),
+            ("loop { let f = fn() { break; }; break; };",    23),
+            ("while true { fn f() { continue; }; break; };", 23),
+        ].iter() {
+            match interpret(src, Scope::new()).exec_result {
+                ExecResult::ParseError(e) => {
+                    assert_eq!((1, *col), (e.line, e.column), "{}", src);
+                    assert_eq!("statement other than 'break' or 'continue' outside a loop", e.expected, "{}", src);
+                },
+                x => panic!("interpret(): {} should have returned ParseError, not {:?}", src, x),
+            }
+        }
+        let src = "for i in 0..3 { if i == 1 { continue; }; { break; }; };";
+        assert_eq!(ExecResult::None, interpret(src, Scope::new()).exec_result);
+
+        // Parameter names must be unique, and parameters without a default value cannot follow
+        // one with a default value
+        for (src, col, expected) in [
+            ("fn f(a, a) {};",              9,  "unused parameter name"),
+            ("let f = fn(a, b = 1, a) {};", 22, "unused parameter name"),
+            ("fn f(a, ...a) {};",           12, "unused parameter name"),
+            ("fn f(a = 1, b) {};",          13, "parameter with a default value"),
+            ("fn f(a, b = 1, c, ...d) {};", 16, "parameter with a default value"),
+        ].iter() {
+            match interpret(src, Scope::new()).exec_result {
+                ExecResult::ParseError(e) => {
+                    assert_eq!((1, *col), (e.line, e.column), "{}", src);
+                    assert_eq!(*expected, e.expected, "{}", src);
+                },
+                x => panic!("interpret(): {} should have returned ParseError, not {:?}", src, x),
+            }
+        }
+    }
+
+    #[test]
+    fn runtime_errors() {
+        assert_eq!(
+            Some(RuntimeErrorKind::UndefinedFunction),
+            error_kind(interpret("return f(1);", Scope::new()).exec_result)
+        );
+        assert_eq!(
+            Some(RuntimeErrorKind::UndefinedVariable),
+            error_kind(interpret("let a = [1]; return b[0];", Scope::new()).exec_result)
+        );
+        assert_eq!(
+            Some(RuntimeErrorKind::TypeMismatch),
+            error_kind(interpret(r#"return 1 - "a";"#, Scope::new()).exec_result)
+        );
+
+        // Equality between Values of different types is not an error
+        assert_eq!(
+            ExecResult::Return(Value::Bool(false)),
+            interpret(r#"return 1 == "1";"#, Scope::new()).exec_result
+        );
+        assert_eq!(
+            ExecResult::Return(Value::Bool(true)),
+            interpret("return null == null;", Scope::new()).exec_result
+        );
+
+        // Errors stop execution, including within loops and Functions
+        let res = interpret("let a = 1; loop { let a = x; }; let b = 2;", Scope::new());
+        assert_eq!(Some(RuntimeErrorKind::UndefinedVariable), error_kind(res.exec_result));
+        assert_eq!(None, res.scope_chain.resolve_var("b"));
+
+        // Errors are located at the failing Expr and record the script Function call stack
+        let src = "fn f(x) {\n  return x + null;\n};\nfn g() { return f(1); };\nlet a = g();";
+        match interpret(src, Scope::new()).exec_result {
+            ExecResult::Error(e) => {
+                assert_eq!(RuntimeErrorKind::TypeMismatch, e.kind);
+                assert_eq!((2, 10), (e.span.line, e.span.column));
+                assert_eq!(vec!["f", "g"], e.stack.iter().map(|x| x.func).collect::<Vec<Ident>>());
+                assert_eq!((4, 17), (e.stack[0].span.line, e.stack[0].span.column));
+                assert_eq!((5, 9), (e.stack[1].span.line, e.stack[1].span.column));
+                assert_eq!(
+                    "line 2, column 10: cannot apply Add to Int and None\n  \
+                     in f() called at line 4, column 17\n  \
+                     in g() called at line 5, column 9",
+                    format!("{}", e)
+                );
+            },
+            x => panic!("interpret() should have returned Error, not {:?}", x),
+        };
+    }
+
+    #[test]
+    fn strict_mode() {
+        let strict = || Config { strict: true, ..Config::default() };
+
+        // Lenient by default
+        let srcs = [
+            "return 1 < true;",
+            r#"return "a" >= 1;"#,
+            r#"let a = [1]; return a["x"];"#,
+            "let a = [1]; return a[1];",
+            r#"let a = {"b": 1}; return a["c"];"#,
+            "let a = 1; return a[0];",
+            "let a = [1]; a[null] = 2;",
+            "let a = 1; a[0] = 2;",
+            "b[0] = 1;",
+        ];
+        for src in srcs.iter() {
+            match interpret(src, Scope::new()).exec_result {
+                ExecResult::Return(Value::None) | ExecResult::None => {},
+                x => panic!("interpret(): {}: should be lenient, not {:?}", src, x),
+            };
+        }
+
+        // Errors in strict mode
+        let kinds = [
+            RuntimeErrorKind::TypeMismatch,
+            RuntimeErrorKind::TypeMismatch,
+            RuntimeErrorKind::TypeMismatch,
+            RuntimeErrorKind::InvalidIndex,
+            RuntimeErrorKind::InvalidIndex,
+            RuntimeErrorKind::TypeMismatch,
+            RuntimeErrorKind::TypeMismatch,
+            RuntimeErrorKind::TypeMismatch,
+            RuntimeErrorKind::UndefinedVariable,
+        ];
+        for (src, kind) in srcs.iter().zip(kinds.iter()) {
+            assert_eq!(
+                Some(*kind),
+                error_kind(interpret_with_config(src, Scope::new(), strict()).exec_result),
+                "{}",
+                src
+            );
+        }
+
+        // Valid operations are unaffected
+        assert_eq!(
+            ExecResult::Return(Value::Int(3)),
+            interpret_with_config(
+                "let a = [1]; a[1] = 2; return a[0] + a[1];",
+                Scope::new(),
+                strict()
+            ).exec_result
+        );
+    }
+
     #[test]
     fn bin_ops() {
 
@@ -418,9 +774,50 @@ mod tests {
 
         // %
         assert_eq!(ExecResult::Return(Value::Int(4)), interpret("return 16   % 6;",    Scope::new()).exec_result);
-        assert_eq!(ExecResult::Return(Value::None),   interpret("return 16   % 12.1;", Scope::new()).exec_result);
-        assert_eq!(ExecResult::Return(Value::None),   interpret("return 16.1 % 12;",   Scope::new()).exec_result);
-        assert_eq!(ExecResult::Return(Value::None),   interpret("return 16.1 % 12.1;", Scope::new()).exec_result);
+        assert_eq!(Some(RuntimeErrorKind::TypeMismatch), error_kind(interpret("return 16   % 12.1;", Scope::new()).exec_result));
+        assert_eq!(Some(RuntimeErrorKind::TypeMismatch), error_kind(interpret("return 16.1 % 12;",   Scope::new()).exec_result));
+        assert_eq!(Some(RuntimeErrorKind::TypeMismatch), error_kind(interpret("return 16.1 % 12.1;", Scope::new()).exec_result));
+    }
+
+    #[test]
+    fn string_ops() {
+        let str_res = |src| interpret(src, Scope::new()).exec_result;
+        let str_val = |s: &str| ExecResult::Return(Value::Str(s.to_string().into()));
+
+        // Concatenation, including of Ints and Reals
+        assert_eq!(str_val("ab"),      str_res(r#"return "a" + "b";"#));
+        assert_eq!(str_val("a\tb"),    str_res(r#"let a = "a\t"; return a + "b";"#));
+        assert_eq!(str_val("n = 12"),  str_res(r#"return "n = " + 12;"#));
+        assert_eq!(str_val("1.5 m"),   str_res(r#"return 1.5 + " m";"#));
+        assert_eq!(str_val("3!"),      str_res(r#"return 1 + 2 + "!";"#));
+        assert_eq!(str_val("!12"),     str_res(r#"return "!" + 1 + 2;"#));
+
+        // Repetition
+        assert_eq!(str_val("ababab"),  str_res(r#"return "ab" * 3;"#));
+        assert_eq!(str_val("--"),      str_res(r#"return 2 * "-";"#));
+        assert_eq!(str_val(""),        str_res(r#"return "ab" * 0;"#));
+        assert_eq!(str_val(""),        str_res(r#"return "ab" * -1;"#));
+
+        // Repetitions too long to be stored are errors
+        for src in [r#"return "ab" * 9223372036854775807;"#, r#"return 4611686018427387904 * "a";"#].iter() {
+            assert_eq!(Some(RuntimeErrorKind::Overflow), error_kind(str_res(src)), "{}", src);
+        }
+        assert_eq!(str_val(""), str_res(r#"return "" * 9223372036854775807;"#));
+
+        // Comparison
+        assert_eq!(ExecResult::Return(Value::Bool(true)),  str_res(r#"return "ab" == "a" + "b";"#));
+        assert_eq!(ExecResult::Return(Value::Bool(true)),  str_res(r#"return "ab" != "abc";"#));
+        assert_eq!(ExecResult::Return(Value::Bool(true)),  str_res(r#"return "abc" < "abd";"#));
+        assert_eq!(ExecResult::Return(Value::Bool(false)), str_res(r#"return "b" <= "abc";"#));
+        assert_eq!(ExecResult::Return(Value::Bool(true)),  str_res(r#"return "b" > "abc";"#));
+        assert_eq!(ExecResult::Return(Value::Bool(true)),  str_res(r#"return "a" >= "a";"#));
+        assert_eq!(ExecResult::Return(Value::Bool(false)), str_res(r#"return "1" == 1;"#));
+
+        // Unsupported operands
+        for src in [r#"return "a" - "b";"#, r#"return "a" * "b";"#, r#"return "a" * 1.5;"#,
+                    r#"return "a" + null;"#, r#"return "a" + true;"#, r#"return "a" / 2;"#].iter() {
+            assert_eq!(Some(RuntimeErrorKind::TypeMismatch), error_kind(str_res(src)), "{}", src);
+        }
     }
 
     #[test]
@@ -439,15 +836,75 @@ mod tests {
         assert_eq!(ExecResult::Return(Value::Bool(true)),  interpret("return true  ^ false;", Scope::new()).exec_result);
         assert_eq!(ExecResult::Return(Value::Bool(true)),  interpret("return false ^ true;",  Scope::new()).exec_result);
         assert_eq!(ExecResult::Return(Value::Bool(false)), interpret("return false ^ false;", Scope::new()).exec_result);
+
+        // Non-Bool operands: only null is false
+        let srcs = [
+            ("return null || 0;",          true),
+            ("return 1 && \"\";",          true),
+            ("return [] && {\"a\": 1};",   true),
+            ("return null && true;",       false),
+            ("return null || null;",       false),
+            ("return 1.5 ^ null;",         true),
+            ("return \"a\" ^ 1;",          false),
+        ];
+        for (src, res) in srcs.iter() {
+            assert_eq!(
+                ExecResult::Return(Value::Bool(*res)),
+                interpret_with_config(src, Scope::new(), Config { strict: true, ..Config::default() }).exec_result,
+                "{}",
+                src
+            );
+        }
+
+        // && and || only evaluate their right-hand operand when required
+        let scopes = interpret(
+            r#"
+            let calls = 0;
+            fn f(x) { calls += 1; return x; };
+            let a = false && f(true);
+            let b = true || f(false);
+            let c = true && f(true);
+            let d = false || f(false);
+            let x = null;
+            let e = x != null && x["k"] == 1;
+            let y = {"k": 1};
+            let g = y != null && y["k"] == 1;
+            "#,
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(2)),        scopes.resolve_var("calls"));
+        assert_eq!(Some(Value::Bool(false)),   scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Bool(true)),    scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Bool(true)),    scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Bool(false)),   scopes.resolve_var("d"));
+        assert_eq!(Some(Value::Bool(false)),   scopes.resolve_var("e"));
+        assert_eq!(Some(Value::Bool(true)),    scopes.resolve_var("g"));
+        assert_eq!(
+            ExecResult::Return(Value::Bool(true)),
+            interpret("return true || undefined_var;", Scope::new()).exec_result
+        );
+
+        // Conditions follow the same truth rules as logical operands
+        let srcs = [
+            ("if 1 { return 1; } else { return 2; };",       1),
+            ("if null { return 1; } else { return 2; };",    2),
+            ("if [] { return 1; } else { return 2; };",      1),
+            ("return if 0 { 1 } else { 2 };",                1),
+            ("return if null { 1 } else { 2 };",             2),
+            ("let n = 3; let x = 1; while x { n -= 1; if n == 0 { x = null; }; }; return n;", 0),
+        ];
+        for (src, res) in srcs.iter() {
+            assert_eq!(ExecResult::Return(Value::Int(*res)), interpret(src, Scope::new()).exec_result, "{}", src);
+        }
     }
 
     #[test]
     fn stmt_block() {
         // Test evaluation of a full StmtBlock with a new Scope
         let scopes = interpret("let abc = 1 + 2; let bcd = 3 + 4; let cde = abc * bcd;", Scope::new()).scope_chain;
-        assert_eq!(Some(&Value::Int(3)),  scopes.resolve_var("abc"));
-        assert_eq!(Some(&Value::Int(7)),  scopes.resolve_var("bcd"));
-        assert_eq!(Some(&Value::Int(21)), scopes.resolve_var("cde"));
+        assert_eq!(Some(Value::Int(3)),  scopes.resolve_var("abc"));
+        assert_eq!(Some(Value::Int(7)),  scopes.resolve_var("bcd"));
+        assert_eq!(Some(Value::Int(21)), scopes.resolve_var("cde"));
     }
 
     #[test]
@@ -457,74 +914,325 @@ mod tests {
             "fn add(a, b) { let c = a + b; return c; let c = 123; }; let res = add(1, 2 + 3);",
             Scope::new()
         ).scope_chain;
-        assert_eq!(Some(&Value::Int(6)), scopes.resolve_var("res"));
+        assert_eq!(Some(Value::Int(6)), scopes.resolve_var("res"));
 
         // Functions without arguments
         let scopes = interpret(
             "fn test() { return 42; }; let res = test();",
             Scope::new()
         ).scope_chain;
-        assert_eq!(Some(&Value::Int(42)), scopes.resolve_var("res"));
+        assert_eq!(Some(Value::Int(42)), scopes.resolve_var("res"));
     }
 
     #[test]
     fn conditionals() {
-        // Test conditional If/IfElse statements
+        // Test conditional If statements, with and without an else
         let mut scopes = interpret(
-            "let a = 1; if 1 == 1 { let a = 2; } else { let a = 3; }; if 1 != 2 { let a = 4; }",
+            "let a = 1; if 1 == 1 { a = 2; } else { a = 3; }; if 1 != 2 { a = 4; }",
             Scope::new()
         ).scope_chain;
-        assert_eq!(Some(&Value::Int(4)), scopes.resolve_var("a"));
-        let mut scopes = interpret("if (1 == 2) || (1 == 1) { let a = 5; };", scopes.pop().unwrap()).scope_chain;
-        assert_eq!(Some(&Value::Int(5)), scopes.resolve_var("a"));
-        let mut scopes = interpret("if (1 == 1) && (2 == 2) { let a = 6; };", scopes.pop().unwrap()).scope_chain;
-        assert_eq!(Some(&Value::Int(6)), scopes.resolve_var("a"));
-        let mut scopes = interpret("if (1 == 1) ^ (2 == 2) { let a = 7; };", scopes.pop().unwrap()).scope_chain;
-        assert_eq!(Some(&Value::Int(6)), scopes.resolve_var("a"));
-        let scopes = interpret("if 1 == 1 ^ 2 == 2 { let a = 8; };", scopes.pop().unwrap()).scope_chain;
-        assert_eq!(Some(&Value::Int(6)), scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Int(4)), scopes.resolve_var("a"));
+        let mut scopes = interpret("if (1 == 2) || (1 == 1) { a = 5; };", scopes.pop().unwrap()).scope_chain;
+        assert_eq!(Some(Value::Int(5)), scopes.resolve_var("a"));
+        let mut scopes = interpret("if (1 == 1) && (2 == 2) { a = 6; };", scopes.pop().unwrap()).scope_chain;
+        assert_eq!(Some(Value::Int(6)), scopes.resolve_var("a"));
+        let mut scopes = interpret("if (1 == 1) ^ (2 == 2) { a = 7; };", scopes.pop().unwrap()).scope_chain;
+        assert_eq!(Some(Value::Int(6)), scopes.resolve_var("a"));
+        let scopes = interpret("if 1 == 1 ^ 2 == 2 { a = 8; };", scopes.pop().unwrap()).scope_chain;
+        assert_eq!(Some(Value::Int(6)), scopes.resolve_var("a"));
+
+        // Test else-if chains: only the first matching branch is executed
+        let scopes = interpret(
+            r#"
+            fn sign(x) {
+                if x < 0 { return "negative"; } else if x == 0 { return "zero"; } else { return "positive"; };
+            };
+            let a = sign(-5); let b = sign(0); let c = sign(5);
+            let d = 0;
+            if d == 0 { d = 1; } else if d == 1 { d = 2; } else if true { d = 3; };
+            "#,
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Str("negative".into())), scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Str("zero".into())),     scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Str("positive".into())), scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Int(1)),                 scopes.resolve_var("d"));
+
+        // Test if expressions
+        let scopes = interpret(
+            r#"
+            let a = 5;
+            let b = if a > 3 { "big" } else { "small" };
+            let c = if a < 3 { 1 } else if a < 10 { 2 } else { 3 };
+            let d = 10 + if a == 5 { 1 } else { 0 } * 2;
+            let e = if null { 1 } else { 2 };
+            "#,
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Str("big".into())), scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Int(2)),            scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Int(12)),           scopes.resolve_var("d"));
+        assert_eq!(Some(Value::Int(2)),            scopes.resolve_var("e"));
     }
 
     #[test]
     fn loops() {
         // Test loop
         let scopes = interpret(
-            "let a = 0; let b = 1; loop { let a = a + 1; let b = b * 2; if a > 5 { break; }; };",
+            "let a = 0; let b = 1; loop { a += 1; b *= 2; if a > 5 { break; }; };",
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(6)),  scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Int(64)), scopes.resolve_var("b"));
+
+        // Test while loop
+        let scopes = interpret(
+            "let a = 0; let b = 1; while a < 5 { a += 1; b *= 2; };",
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(5)),  scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Int(32)), scopes.resolve_var("b"));
+
+        // Test for loops over lists, dict keys (in sorted order) and ranges
+        let scopes = interpret(
+            r#"
+            let a = 0;
+            for x in [1, 2, 3] { a += x; };
+            let b = "";
+            for k in {"z": 1, "a": 2, "m": 3} { b += k; };
+            let c = 0;
+            for i in 1..5 { c += i; };
+            for i in 5..1 { c = 0; };
+            "#,
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(6)),               scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Str("amz".into())),    scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Int(10)),              scopes.resolve_var("c"));
+
+        // Loop variables only exist within the loop
+        assert_eq!(None, scopes.resolve_var("x"));
+        assert_eq!(None, scopes.resolve_var("i"));
+
+        // Test continue and break within each kind of loop
+        let scopes = interpret(
+            r#"
+            let a = 0;
+            for i in 0..10 { if i % 2 == 0 { continue; }; if i > 7 { break; }; a += i; };
+            let b = 0;
+            let n = 0;
+            while n < 10 { n += 1; if n % 2 == 0 { continue; }; b += n; };
+            let c = 0;
+            loop { c += 1; if c < 3 { continue; }; break; };
+            "#,
             Scope::new()
         ).scope_chain;
-        assert_eq!(Some(&Value::Int(6)),  scopes.resolve_var("a"));
-        assert_eq!(Some(&Value::Int(64)), scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Int(16)), scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Int(25)), scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Int(3)),  scopes.resolve_var("c"));
+
+        // Test Return from within loops
+        assert_eq!(
+            ExecResult::Return(Value::Int(3)),
+            interpret(
+                "fn f() { for x in [1, 3, 5] { if x > 2 { return x; }; }; }; return f();",
+                Scope::new()
+            ).exec_result
+        );
+        assert_eq!(
+            ExecResult::Return(Value::Int(4)),
+            interpret("let a = 0; while true { a += 1; if a == 4 { return a; }; };", Scope::new()).exec_result
+        );
+
+        // Test iteration over invalid Values
+        assert_eq!(
+            Some(RuntimeErrorKind::TypeMismatch),
+            error_kind(interpret("for x in 1 { }", Scope::new()).exec_result)
+        );
+        assert_eq!(
+            Some(RuntimeErrorKind::TypeMismatch),
+            error_kind(interpret("for x in 0..1.5 { }", Scope::new()).exec_result)
+        );
     }
 
     #[test]
     fn unary_ops() {
         // Test unary operators
         let scopes = interpret("let a = !(1 == 1); let b = !(2 < 1);", Scope::new()).scope_chain;
-        assert_eq!(Some(&Value::Bool(false)), scopes.resolve_var("a"));
-        assert_eq!(Some(&Value::Bool(true)), scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Bool(false)), scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Bool(true)), scopes.resolve_var("b"));
 
         // Test unary operators and Boolean literals
         let scopes = interpret("let a = true; let b = false; let c = !a; let d = !a && !b;", Scope::new()).scope_chain;
-        assert_eq!(Some(&Value::Bool(true)),  scopes.resolve_var("a"));
-        assert_eq!(Some(&Value::Bool(false)), scopes.resolve_var("b"));
-        assert_eq!(Some(&Value::Bool(false)), scopes.resolve_var("c"));
-        assert_eq!(Some(&Value::Bool(false)), scopes.resolve_var("d"));
+        assert_eq!(Some(Value::Bool(true)),  scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Bool(false)), scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Bool(false)), scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Bool(false)), scopes.resolve_var("d"));
+
+        // Test numeric unary operators, which bind more tightly than any binary operator
+        let scopes = interpret(
+            "let a = 5; let b = -a; let c = +a; let d = ~a; let e = -(a + 0.5); let f = -a * -2 + --a; let g = -[3][0];",
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(-5)),    scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Int(5)),     scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Int(-6)),    scopes.resolve_var("d"));
+        assert_eq!(Some(Value::Real(-5.5)), scopes.resolve_var("e"));
+        assert_eq!(Some(Value::Int(15)),    scopes.resolve_var("f"));
+        assert_eq!(Some(Value::Int(-3)),    scopes.resolve_var("g"));
+
+        // Numeric unary operators require numeric operands (Int only for ~)
+        for src in ["return -\"a\";", "return +null;", "return ~1.5;", "return -[1];"].iter() {
+            assert_eq!(
+                Some(RuntimeErrorKind::TypeMismatch),
+                error_kind(interpret(src, Scope::new()).exec_result),
+                "{}",
+                src
+            );
+        }
     }
 
     #[test]
-    fn native_functions() {
-        struct TestFunc {};
-        impl NativeFunction for TestFunc {
-            fn execute<'src>(&self, _scopes: &mut ScopeChain<'src>, args: &[Value<'src>]) -> Value<'src> {
-                match args[0] {
-                    Value::Int(x) => Value::Int(x + 40),
-                    _ => Value::None,
-                }
+    fn power_and_floor_division() {
+        let scopes = interpret(
+            "let a = 2 ** 10; let b = 2 ** 3 ** 2; let c = 2 ** -2; let d = 2.0 ** 0.5; let e = -3 ** 3; let f = 7 ~/ 2; let g = -7 ~/ 2; let h = 7 ~/ -2; let i = 7.5 ~/ 2; let j = 1 ** 1_000_000_000_000;",
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(1024)),                scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Int(512)),                 scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Real(0.25)),               scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Real(2f64.sqrt())),        scopes.resolve_var("d"));
+        assert_eq!(Some(Value::Int(-27)),                 scopes.resolve_var("e"));
+        assert_eq!(Some(Value::Int(3)),                   scopes.resolve_var("f"));
+        assert_eq!(Some(Value::Int(-4)),                  scopes.resolve_var("g"));
+        assert_eq!(Some(Value::Int(-4)),                  scopes.resolve_var("h"));
+        assert_eq!(Some(Value::Real(3.0)),                scopes.resolve_var("i"));
+        assert_eq!(Some(Value::Int(1)),                   scopes.resolve_var("j"));
+
+        let cases = [
+            ("return 2 ** 64;", RuntimeErrorKind::Overflow),
+            ("return (-2) ** 1_000_000_000_000;", RuntimeErrorKind::Overflow),
+            ("return 1 ~/ 0;", RuntimeErrorKind::DivisionByZero),
+            ("return \"a\" ** 2;", RuntimeErrorKind::TypeMismatch),
+            ("return [1] ~/ 2;", RuntimeErrorKind::TypeMismatch),
+        ];
+        for &(src, kind) in cases.iter() {
+            assert_eq!(Some(kind), error_kind(interpret(src, Scope::new()).exec_result), "{}", src);
+        }
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let (max, min) = (i64::MAX, i64::MIN);
+        let cases = [
+            (format!("return {} + 1;", max),   Value::Int(min), Value::Int(max)),
+            (format!("return {} - 1;", min),   Value::Int(max), Value::Int(min)),
+            (format!("return {} * -2;", max),  Value::Int(2),   Value::Int(min)),
+            (format!("return -({});", min),    Value::Int(min), Value::Int(max)),
+            (format!("return {} ~/ -1;", min), Value::Int(min), Value::Int(max)),
+            ("return 2 ** 63;".to_string(),    Value::Int(min), Value::Int(max)),
+            ("return (-3) ** 41;".to_string(), Value::Int((-3i64).wrapping_pow(41)), Value::Int(min)),
+            ("return 3 ** 64;".to_string(),    Value::Int(3i64.wrapping_pow(64)),    Value::Int(max)),
+        ];
+        for (src, wrapped, saturated) in cases.iter() {
+            // Overflow is an error by default
+            assert_eq!(
+                Some(RuntimeErrorKind::Overflow),
+                error_kind(interpret(src, Scope::new()).exec_result),
+                "{}",
+                src
+            );
+
+            let wrapping = Config { arithmetic: Arithmetic::Wrapping, ..Config::default() };
+            assert_eq!(
+                ExecResult::Return(wrapped.clone()),
+                interpret_with_config(src, Scope::new(), wrapping).exec_result,
+                "{}",
+                src
+            );
+
+            let saturating = Config { arithmetic: Arithmetic::Saturating, ..Config::default() };
+            assert_eq!(
+                ExecResult::Return(saturated.clone()),
+                interpret_with_config(src, Scope::new(), saturating).exec_result,
+                "{}",
+                src
+            );
+        }
+
+        // Division by 0 is an error in every mode, and i64::MIN % -1 does not overflow
+        for &arithmetic in [Arithmetic::Checked, Arithmetic::Wrapping, Arithmetic::Saturating].iter() {
+            let config = Config { arithmetic, ..Config::default() };
+            for src in ["return 1 % 0;", "return 1 ~/ 0;"].iter() {
+                assert_eq!(
+                    Some(RuntimeErrorKind::DivisionByZero),
+                    error_kind(interpret_with_config(src, Scope::new(), config.clone()).exec_result),
+                    "{}",
+                    src
+                );
             }
-            fn as_any(&self) -> &dyn Any {
-                self
+            assert_eq!(
+                ExecResult::Return(Value::Int(0)),
+                interpret_with_config(&format!("return {} % -1;", min), Scope::new(), config).exec_result
+            );
+        }
+
+        // Compound assignment is checked in the same way
+        let src = format!("let a = {}; a += 1;", max);
+        assert_eq!(Some(RuntimeErrorKind::Overflow), error_kind(interpret(&src, Scope::new()).exec_result));
+    }
+
+    #[test]
+    fn bitwise_ops() {
+        let scopes = interpret(
+            "let a = 0b1100 & 0b1010; let b = 0b1100 | 0b1010; let c = 0xff_ff; let d = 1 << 4; let e = -16 >> 2; let f = 6 & 3 == 2; let g = 1 & 2 == 0 && 0 | 1 == 1;",
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(0b1000)), scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Int(0b1110)), scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Int(65535)),  scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Int(16)),     scopes.resolve_var("d"));
+        assert_eq!(Some(Value::Int(-4)),     scopes.resolve_var("e"));
+        assert_eq!(Some(Value::Bool(true)),  scopes.resolve_var("f"));
+        assert_eq!(Some(Value::Bool(true)),  scopes.resolve_var("g"));
+
+        // Bitwise operators require Int operands, and shift amounts must be in range
+        for src in ["return 1 & 1.0;", "return true | false;", "return \"a\" << 1;"].iter() {
+            assert_eq!(
+                Some(RuntimeErrorKind::TypeMismatch),
+                error_kind(interpret(src, Scope::new()).exec_result),
+                "{}",
+                src
+            );
+        }
+        for src in ["return 1 << -1;", "return 1 >> 4096;"].iter() {
+            assert_eq!(
+                Some(RuntimeErrorKind::Overflow),
+                error_kind(interpret(src, Scope::new()).exec_result),
+                "{}",
+                src
+            );
+        }
+    }
+
+    struct TestFunc {}
+    impl NativeFunction for TestFunc {
+        fn execute<'src>(&self, _scopes: &mut ScopeChain<'src>, args: &[Value<'src>]) -> Value<'src> {
+            match args[0] {
+                Value::Int(x) => Value::Int(x + 40),
+                _ => Value::None,
             }
-        };
+        }
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+        fn arity(&self) -> Arity {
+            Arity::at_least(1)
+        }
+    }
+
+    #[test]
+    fn native_functions() {
         let test_func = TestFunc {};
         let mut scope = Scope::new();
         scope
@@ -532,23 +1240,406 @@ mod tests {
             .insert("test_func", Rc::new(test_func));
 
         let scopes = interpret("let a = test_func(1) + 1; let b = test_func(12) * 3;", scope).scope_chain;
-        assert_eq!(Some(&Value::Int(42)),  scopes.resolve_var("a"));
-        assert_eq!(Some(&Value::Int(156)), scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Int(42)),  scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Int(156)), scopes.resolve_var("b"));
+
+        // The number of arguments is checked against the NativeFunction's Arity
+        let mut scope = Scope::new();
+        scope.native_funcs.insert("test_func", Rc::new(TestFunc {}));
+        match interpret("let a = 1;\nlet b = test_func();", scope).exec_result {
+            ExecResult::Error(e) => {
+                assert_eq!(RuntimeErrorKind::ArityMismatch, e.kind);
+                assert_eq!("expected at least 1 argument, found 0", e.message);
+                assert_eq!((2, 9), (e.span.line, e.span.column));
+            },
+            x => panic!("interpret() should have returned Error, not {:?}", x),
+        };
+    }
+
+    #[test]
+    fn first_class_functions() {
+        let src = "
+            fn add(a, b) { return a + b; };
+            fn apply(f, a, b) { return f(a, b); };
+            let mul = fn(a, b) { return a * b; };
+            let ops = {\"add\": add, \"mul\": mul};
+
+            let a = apply(add, 1, 2);
+            let b = apply(mul, 3, 4);
+            let c = ops.add(5, 6) + ops[\"mul\"](7, 8);
+            let d = (fn(x) { return x * 2; })(21);
+            let e = add == add;
+            let f = fn() {} == fn() {};
+            let g = mul;
+            let h = g(2, 3);
+            let i = apply(test_func, 2, 0);
+        ";
+        let mut scope = Scope::new();
+        scope.native_funcs.insert("test_func", Rc::new(TestFunc {}));
+        let scopes = interpret(src, scope).scope_chain;
+        assert_eq!(Some(Value::Int(3)),      scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Int(12)),     scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Int(67)),     scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Int(42)),     scopes.resolve_var("d"));
+        assert_eq!(Some(Value::Bool(true)),  scopes.resolve_var("e"));
+        assert_eq!(Some(Value::Bool(false)), scopes.resolve_var("f"));
+        assert_eq!(scopes.resolve_var("mul"), scopes.resolve_var("g"));
+        assert_eq!(Some(Value::Int(6)),      scopes.resolve_var("h"));
+        assert_eq!(Some(Value::Int(42)),     scopes.resolve_var("i"));
+
+        // Only Funcs can be called
+        for src in ["let f = 1; f();", "return [1](0);", "let a = [1]; a[0]();"].iter() {
+            assert_eq!(
+                Some(RuntimeErrorKind::TypeMismatch),
+                error_kind(interpret(src, Scope::new()).exec_result),
+                "{}",
+                src
+            );
+        }
+
+        // Errors within anonymous Functions are reported with an anonymous stack frame
+        match interpret("let f = [fn() { return 1 + null; }];\nf[0]();", Scope::new()).exec_result {
+            ExecResult::Error(e) => assert_eq!(vec!["<anonymous>"], e.stack.iter().map(|x| x.func).collect::<Vec<_>>()),
+            x => panic!("interpret() should have returned Error, not {:?}", x),
+        }
+    }
+
+    #[test]
+    fn closures() {
+        let src = "
+            fn make_counter() {
+                let n = 0;
+                return fn() { n += 1; return n; };
+            };
+            let c1 = make_counter();
+            let c2 = make_counter();
+            c1(); c1();
+            let a = c1();
+            let b = c2();
+
+            fn make_adder(x) { return fn(y) { return x + y; }; };
+            let add5 = make_adder(5);
+            let c = add5(1) + make_adder(10)(2);
+
+            fn make_account() {
+                let balance = 0;
+                fn deposit(x) { balance += x; };
+                let get = fn() { return balance; };
+                deposit(10);
+                return {\"deposit\": deposit, \"get\": get};
+            };
+            let acc = make_account();
+            acc.deposit(5);
+            let d = acc.get();
+
+            fn memoise(f) {
+                let cache = {};
+                return fn(k) {
+                    if cache[k] == null { cache[k] = f(k); };
+                    return cache[k];
+                };
+            };
+            let calls = 0;
+            let slow = memoise(fn(k) { calls += 1; return k * 2; });
+            let e = slow(\"a\") + slow(\"a\") + slow(\"b\");
+
+            let fs = [];
+            for i in 0..3 { fs[i] = fn() { return i; }; };
+            let f = [fs[0](), fs[1](), fs[2]()];
+        ";
+        let scopes = interpret(src, Scope::new()).scope_chain;
+        assert_eq!(Some(Value::Int(3)),  scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Int(1)),  scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Int(18)), scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Int(15)), scopes.resolve_var("d"));
+        assert_eq!(Some(Value::Str("aaaabb".into())), scopes.resolve_var("e"));
+        assert_eq!(Some(Value::Int(2)),  scopes.resolve_var("calls"));
+        assert_eq!(Some(Value::List(vec![Value::Int(0), Value::Int(1), Value::Int(2)])), scopes.resolve_var("f"));
+
+        // Functions see the variables of the Scopes in which they were created, not those of
+        // their caller
+        let src = "
+            fn get_x() { return x; };
+            fn call_with_x() { let x = 1; return get_x(); };
+            return call_with_x();
+        ";
+        assert_eq!(
+            Some(RuntimeErrorKind::UndefinedVariable),
+            error_kind(interpret(src, Scope::new()).exec_result)
+        );
+        let src = "
+            let x = 1;
+            fn get_x() { return x; };
+            fn call_with_x() { let x = 2; return get_x(); };
+            return call_with_x();
+        ";
+        assert_eq!(ExecResult::Return(Value::Int(1)), interpret(src, Scope::new()).exec_result);
+
+        // A Function is equal to itself wherever it is stored, including copies made within the
+        // Function call that created it
+        for src in [
+            "fn f() { let a = fn() {}; let b = a; return a == b; }; return f();",
+            "fn f() { fn g() {}; let h = g; return g == h; }; return f();",
+            "fn f() { let a = fn() {}; let l = [a]; return [a][0] == l[0] && a != fn() {}; }; return f();",
+            "let a = fn() {}; let b = a; return a == b;",
+        ].iter() {
+            assert_eq!(ExecResult::Return(Value::Bool(true)), interpret(src, Scope::new()).exec_result, "{}", src);
+        }
+
+        // Functions returned from the call that created them keep its Scope alive, and are the
+        // same Function whenever they are used as a Value, but the Scope is freed once they are
+        // dropped
+        for name in ["deposit", "get"].iter() {
+            let src = format!("
+                fn make_account() {{
+                    let balance = 0;
+                    fn deposit(x) {{ balance += x; return balance; }};
+                    let get = fn() {{ return balance; }};
+                    return [{0}, {0} == {0}, deposit, get];
+                }};
+                let res = make_account();
+                let f = res[0];
+                res[2](10);
+                res[2](5);
+                let a = res[3]();
+            ", name);
+            let scopes = interpret(&src, Scope::new()).scope_chain;
+            assert_eq!(Some(Value::Int(15)), scopes.resolve_var("a"), "{}", name);
+            match scopes.resolve_var("res") {
+                Some(Value::List(x)) => assert_eq!(Value::Bool(true), x[1], "{}", name),
+                x => panic!("res should be a List, not {:?}", x),
+            }
+            let scope = match scopes.resolve_var("f") {
+                Some(Value::Func(Callable::Func(f))) => Rc::clone(f.env.last().unwrap()),
+                x => panic!("{} should be a Function, not {:?}", name, x),
+            };
+            assert!(scope.borrow().vars.contains_key("balance"), "{}", name);
+            drop(scopes);
+            assert_eq!(1, Rc::strong_count(&scope), "{}", name);
+        }
+    }
+
+    #[test]
+    fn function_hoisting() {
+        // Functions can be called before their declaration within the same block
+        let src = "
+            let a = double(21);
+            fn is_even(n) { if n == 0 { return true; }; return is_odd(n - 1); };
+            fn is_odd(n) { if n == 0 { return false; }; return is_even(n - 1); };
+            let b = [is_even(10), is_odd(7), is_even(3)];
+            let c = 0;
+            if true {
+                c = triple(2);
+                fn triple(x) { return x * 3; };
+            };
+            fn double(x) { return x * 2; };
+            let y = 5;
+            let d = get_y();
+            fn get_y() { return y; };
+        ";
+        let scopes = interpret(src, Scope::new()).scope_chain;
+        assert_eq!(Some(Value::Int(42)), scopes.resolve_var("a"));
+        assert_eq!(
+            Some(Value::List(vec![Value::Bool(true), Value::Bool(true), Value::Bool(false)])),
+            scopes.resolve_var("b")
+        );
+        assert_eq!(Some(Value::Int(6)), scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Int(5)), scopes.resolve_var("d"));
+        assert!(scopes.resolve_func("triple").is_none());
+
+        // Functions declared within a Function body are hoisted within that body
+        assert_eq!(
+            ExecResult::Return(Value::Int(9)),
+            interpret("fn f() { return g(); fn g() { return 9; }; }; return f();", Scope::new()).exec_result
+        );
+
+        // A Function may be declared again in an inner block, but only once per block
+        assert_eq!(
+            ExecResult::Return(Value::Int(2)),
+            interpret("fn f() { return 1; }; { fn f() { return 2; }; return f(); };", Scope::new()).exec_result
+        );
+        let res = interpret("let a = 1;\nfn f() { return 1; };\nfn f() { return 2; };", Scope::new());
+        match res.exec_result {
+            ExecResult::Error(e) => {
+                assert_eq!(RuntimeErrorKind::DuplicateDefinition, e.kind);
+                assert_eq!((3, 1), (e.span.line, e.span.column));
+            },
+            x => panic!("interpret() should have returned Error, not {:?}", x),
+        };
+
+        // The block containing the duplicate declaration is not executed at all
+        assert_eq!(None, res.scope_chain.resolve_var("a"));
+        assert!(res.scope_chain.resolve_func("f").is_none());
+        let res = interpret("let a = 1; if true { a = 2; fn f() {}; fn f() {}; };", Scope::new());
+        assert_eq!(Some(RuntimeErrorKind::DuplicateDefinition), error_kind(res.exec_result));
+        assert_eq!(Some(Value::Int(1)), res.scope_chain.resolve_var("a"));
+    }
+
+    #[test]
+    fn function_signatures() {
+        let src = "
+            fn f(a, b = 2, c = a + b) { return [a, b, c]; };
+            let a = f(1);
+            let b = f(1, 5);
+            let c = f(1, 5, 0);
+
+            fn sum(first, ...rest) {
+                let total = first;
+                for x in rest { total += x; };
+                return [total, rest];
+            };
+            let d = sum(1);
+            let e = sum(1, 2, 3);
+
+            let n = 10;
+            let g = fn(x = n, ...xs) { return [x, xs]; };
+            n = 20;
+            let h = [g(), g(1, 2)];
+        ";
+        let scopes = interpret(src, Scope::new()).scope_chain;
+        let ints = |xs: &[i64]| Value::List(xs.iter().map(|x| Value::Int(*x)).collect());
+        assert_eq!(Some(ints(&[1, 2, 3])), scopes.resolve_var("a"));
+        assert_eq!(Some(ints(&[1, 5, 6])), scopes.resolve_var("b"));
+        assert_eq!(Some(ints(&[1, 5, 0])), scopes.resolve_var("c"));
+        assert_eq!(Some(Value::List(vec![Value::Int(1), ints(&[])])),     scopes.resolve_var("d"));
+        assert_eq!(Some(Value::List(vec![Value::Int(6), ints(&[2, 3])])), scopes.resolve_var("e"));
+        assert_eq!(
+            Some(Value::List(vec![
+                Value::List(vec![Value::Int(20), ints(&[])]),
+                Value::List(vec![Value::Int(1), ints(&[2])]),
+            ])),
+            scopes.resolve_var("h")
+        );
+
+        // Calls must pass an argument for every parameter without a default value, and no more
+        // arguments than there are parameters unless there is a rest parameter. These errors are
+        // raised before the Function starts, so are not reported within it
+        for (src, msg) in [
+            ("fn f(a, b) {}; f(1);",                "expected 2 arguments, found 1"),
+            ("fn f() {}; f(1);",                    "expected 0 arguments, found 1"),
+            ("fn f(a, b = 1) {}; f(1, 2, 3);",      "expected 1 to 2 arguments, found 3"),
+            ("fn f(a, b = 1, c = 2) {}; f();",      "expected 1 to 3 arguments, found 0"),
+            ("fn f(a, ...b) {}; f();",              "expected at least 1 argument, found 0"),
+            ("let f = fn(a) {}; [f][0]();",         "expected 1 argument, found 0"),
+        ].iter() {
+            match interpret(src, Scope::new()).exec_result {
+                ExecResult::Error(e) => {
+                    assert_eq!(RuntimeErrorKind::ArityMismatch, e.kind, "{}", src);
+                    assert_eq!(*msg, e.message, "{}", src);
+                    assert!(e.stack.is_empty(), "{}", src);
+                },
+                x => panic!("interpret(): {} should have returned Error, not {:?}", src, x),
+            }
+        }
+
+        // Errors within default values are reported within the called Function
+        match interpret("fn f(a = 1 + null) {};\nf();", Scope::new()).exec_result {
+            ExecResult::Error(e) => {
+                assert_eq!(RuntimeErrorKind::TypeMismatch, e.kind);
+                assert_eq!((1, 10), (e.span.line, e.span.column));
+                assert_eq!(vec!["f"], e.stack.iter().map(|x| x.func).collect::<Vec<_>>());
+            },
+            x => panic!("interpret() should have returned Error, not {:?}", x),
+        };
+    }
+
+    struct TestJoin {}
+    impl NativeFunction for TestJoin {
+        fn execute<'src>(&self, scopes: &mut ScopeChain<'src>, args: &[Value<'src>]) -> Value<'src> {
+            self.execute_named(scopes, args, &[])
+        }
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+        fn named_params(&self) -> &[&'static str] {
+            &["sep"]
+        }
+        fn execute_named<'src>(
+            &self,
+            _scopes: &mut ScopeChain<'src>,
+            args: &[Value<'src>],
+            named: &[(Ident<'src>, Value<'src>)],
+        ) -> Value<'src> {
+            let sep = match named.iter().find(|x| x.0 == "sep") {
+                Some((_, Value::Str(x))) => x.to_string(),
+                _ => ",".to_string(),
+            };
+            let items = args.iter().map(|x| match x {
+                Value::Str(x) => x.to_string(),
+                x => format!("{:?}", x),
+            });
+            Value::Str(items.collect::<Vec<String>>().join(&sep).into())
+        }
+    }
+
+    #[test]
+    fn named_arguments() {
+        let src = "
+            fn connect(host, port = 80, secure = false) { return [host, port, secure]; };
+            let a = connect(host: \"x\", port: 8080);
+            let b = connect(\"y\", secure: true);
+            let c = connect(secure: true, host: \"z\");
+            fn f(a, b = 1, ...rest) { return [a, b, rest]; };
+            let d = f(a: 2);
+            let e = f(3, 4, 5, 6);
+            let g = (fn(x, y) { return x - y; })(y: 1, x: 3);
+            let h = join(\"a\", \"b\", sep: \"-\") + join(\"c\", \"d\");
+        ";
+        let mut scope = Scope::new();
+        scope.native_funcs.insert("join", Rc::new(TestJoin {}));
+        let scopes = interpret(src, scope).scope_chain;
+        let list = |xs: Vec<Value<'static>>| Some(Value::List(xs));
+        assert_eq!(list(vec![Value::Str("x".into()), Value::Int(8080), Value::Bool(false)]), scopes.resolve_var("a"));
+        assert_eq!(list(vec![Value::Str("y".into()), Value::Int(80),   Value::Bool(true)]),  scopes.resolve_var("b"));
+        assert_eq!(list(vec![Value::Str("z".into()), Value::Int(80),   Value::Bool(true)]),  scopes.resolve_var("c"));
+        assert_eq!(list(vec![Value::Int(2), Value::Int(1), Value::List(vec![])]), scopes.resolve_var("d"));
+        assert_eq!(
+            list(vec![Value::Int(3), Value::Int(4), Value::List(vec![Value::Int(5), Value::Int(6)])]),
+            scopes.resolve_var("e")
+        );
+        assert_eq!(Some(Value::Int(2)), scopes.resolve_var("g"));
+        assert_eq!(Some(Value::Str("a-bc,d".into())), scopes.resolve_var("h"));
+
+        // Named arguments must match a parameter that has not been passed an argument already,
+        // and every parameter without a default value must still be passed an argument
+        for (src, kind, msg) in [
+            ("fn f(a) {}; f(b: 1);",          RuntimeErrorKind::InvalidArgument, "unknown argument 'b'"),
+            ("fn f(a) {}; f(a: 1, a: 2);",    RuntimeErrorKind::InvalidArgument, "argument 'a' passed more than once"),
+            ("fn f(a) {}; f(1, a: 2);",       RuntimeErrorKind::InvalidArgument, "argument 'a' passed more than once"),
+            ("fn f(a, ...r) {}; f(r: []);",   RuntimeErrorKind::InvalidArgument, "unknown argument 'r'"),
+            ("fn f(a, b) {}; f(b: 1);",       RuntimeErrorKind::ArityMismatch,   "missing argument 'a'"),
+            ("fn f(a) {}; f(1, 2, a: 3);",    RuntimeErrorKind::ArityMismatch,   "expected 1 argument, found 2"),
+            ("join(sep: 1, sep: 2);",         RuntimeErrorKind::InvalidArgument, "argument 'sep' passed more than once"),
+            ("join(1, end: 2);",              RuntimeErrorKind::InvalidArgument, "unknown argument 'end'"),
+            ("println(end: 2);",              RuntimeErrorKind::InvalidArgument, "unknown argument 'end'"),
+        ].iter() {
+            let mut scope = Scope::new();
+            insert_test_functions(&mut scope);
+            scope.native_funcs.insert("join", Rc::new(TestJoin {}));
+            match interpret(src, scope).exec_result {
+                ExecResult::Error(e) => {
+                    assert_eq!(*kind, e.kind, "{}", src);
+                    assert_eq!(*msg, e.message, "{}", src);
+                    assert!(e.stack.is_empty(), "{}", src);
+                },
+                x => panic!("interpret(): {} should have returned Error, not {:?}", src, x),
+            }
+        }
     }
 
     #[test]
     fn lists() {
         let scopes = interpret("let a = [1, \"test\", 2]; let b = a[1];", Scope::new()).scope_chain;
         assert_eq!(
-            Some(&Value::List(vec![
+            Some(Value::List(vec![
                 Value::Int(1),
-                Value::Str("test"),
+                Value::Str("test".into()),
                 Value::Int(2)
             ])),
             scopes.resolve_var("a")
         );
         assert_eq!(
-            Some(&Value::Str("test")),
+            Some(Value::Str("test".into())),
             scopes.resolve_var("b")
         );
 
@@ -557,19 +1648,19 @@ mod tests {
             Scope::new()
         ).scope_chain;
         assert_eq!(
-            Some(&Value::List(vec![
+            Some(Value::List(vec![
                 Value::Int(42),
-                Value::Str("test"),
+                Value::Str("test".into()),
                 Value::Int(2),
                 Value::None,
-                Value::Str("test2"),
+                Value::Str("test2".into()),
             ])),
             scopes.resolve_var("a")
         );
-        assert_eq!(Some(&Value::Int(42)), scopes.resolve_var("b"));
-        assert_eq!(Some(&Value::None),    scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Int(42)), scopes.resolve_var("b"));
+        assert_eq!(Some(Value::None),    scopes.resolve_var("c"));
         assert_eq!(
-            Some(&Value::Str("test2")),
+            Some(Value::Str("test2".into())),
             scopes.resolve_var("d")
         );
     }
@@ -580,11 +1671,96 @@ mod tests {
             "let a = {\"d1\": 1 + 2, \"d2\": \"second\"}; let b = a[\"d1\"]; a[\"d2\"] = \"third\"; a[\"d3\"] = \"fourth\";",
             Scope::new()
         ).scope_chain;
-        let mut expected = HashMap::<Ident, Value>::new();
-        expected.insert("d1", Value::Int(3));
-        expected.insert("d2", Value::Str("third"));
-        expected.insert("d3", Value::Str("fourth"));
-        assert_eq!(&Value::Dict(expected), scopes.resolve_var("a").unwrap());
-        assert_eq!(Some(&Value::Int(3)),   scopes.resolve_var("b"));
+        let mut expected = HashMap::<Cow<str>, Value>::new();
+        expected.insert("d1".into(), Value::Int(3));
+        expected.insert("d2".into(), Value::Str("third".into()));
+        expected.insert("d3".into(), Value::Str("fourth".into()));
+        assert_eq!(Value::Dict(expected), scopes.resolve_var("a").unwrap());
+        assert_eq!(Some(Value::Int(3)),   scopes.resolve_var("b"));
+    }
+
+    #[test]
+    fn nested_collections() {
+        // Reads through chains of indexes and member accesses
+        let scopes = interpret(
+            r#"
+            let grid = [[1, 2], [3, 4]];
+            let cfg = {"net": {"hosts": ["a", "b"], "port": 8080}};
+            fn f() { return [10, [20, 30]]; };
+            let a = grid[1][0];
+            let b = cfg.net.hosts[1];
+            let c = cfg["net"].port;
+            let d = f()[1][1];
+            let e = [[5]][0][0] + {"x": [6]}.x[0];
+            let g = grid[5][0];
+            let h = cfg.missing.port;
+            "#,
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(Some(Value::Int(3)),          scopes.resolve_var("a"));
+        assert_eq!(Some(Value::Str("b".into())), scopes.resolve_var("b"));
+        assert_eq!(Some(Value::Int(8080)),       scopes.resolve_var("c"));
+        assert_eq!(Some(Value::Int(30)),         scopes.resolve_var("d"));
+        assert_eq!(Some(Value::Int(11)),         scopes.resolve_var("e"));
+        assert_eq!(Some(Value::None),            scopes.resolve_var("g"));
+        assert_eq!(Some(Value::None),            scopes.resolve_var("h"));
+
+        // Writes through chains of indexes and member accesses
+        let scopes = interpret(
+            r#"
+            let grid = [[1, 2], [3, 4]];
+            let cfg = {"net": {"port": 8080}};
+            grid[0][1] = 5;
+            grid[1][3] = 6;
+            grid[1][0] += 10;
+            cfg["net"]["port"] = 80;
+            cfg.net.host = "localhost";
+            cfg.net.port += 1;
+            "#,
+            Scope::new()
+        ).scope_chain;
+        assert_eq!(
+            Some(Value::List(vec![
+                Value::List(vec![Value::Int(1), Value::Int(5)]),
+                Value::List(vec![Value::Int(13), Value::Int(4), Value::None, Value::Int(6)]),
+            ])),
+            scopes.resolve_var("grid")
+        );
+        let mut net = HashMap::<Cow<str>, Value>::new();
+        net.insert("port".into(), Value::Int(81));
+        net.insert("host".into(), Value::Str("localhost".into()));
+        let mut cfg = HashMap::<Cow<str>, Value>::new();
+        cfg.insert("net".into(), Value::Dict(net));
+        assert_eq!(Some(Value::Dict(cfg)), scopes.resolve_var("cfg"));
+
+        // Invalid writes are ignored, unless in strict mode
+        let src = "let a = [[1]]; a[1][0] = 2; a[0][-1] = 3; a[0].x = 4; return a;";
+        assert_eq!(
+            ExecResult::Return(Value::List(vec![Value::List(vec![Value::Int(1)])])),
+            interpret(src, Scope::new()).exec_result
+        );
+        let strict = Config { strict: true, ..Config::default() };
+        let kinds = [
+            RuntimeErrorKind::InvalidIndex,
+            RuntimeErrorKind::InvalidIndex,
+            RuntimeErrorKind::TypeMismatch,
+        ];
+        for (src, kind) in ["a[1][0] = 2;", "a[0][-1] = 3;", "a[0].x = 4;"].iter().zip(kinds.iter()) {
+            let src = format!("let a = [[1]]; {}", src);
+            let res = interpret_with_config(&src, Scope::new(), strict.clone()).exec_result;
+            assert_eq!(Some(*kind), error_kind(res), "{}", src);
+        }
+
+        // Writes beyond the end of a List extend it, but a List that cannot be extended that far
+        // is an error even outside strict mode
+        let src = "let a = []; a[2000] = 1; return a;";
+        let mut items = vec![Value::None; 2000];
+        items.push(Value::Int(1));
+        assert_eq!(ExecResult::Return(Value::List(items)), interpret(src, Scope::new()).exec_result);
+        let src = "let a = [[1]]; a[0][9223372036854775806] = 5;";
+        for config in [Config::default(), strict].iter() {
+            let res = interpret_with_config(src, Scope::new(), config.clone()).exec_result;
+            assert_eq!(Some(RuntimeErrorKind::Overflow), error_kind(res));
+        }
     }
 }
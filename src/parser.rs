@@ -6,12 +6,455 @@ use core::ops::Neg;
 #[cfg(feature = "no_std")]
 use alloc::boxed::Box;
 #[cfg(feature = "no_std")]
+use alloc::string::String;
+#[cfg(feature = "no_std")]
 use alloc::vec::Vec;
 
-use nom::{alpha, digit, digit0};
+#[cfg(not(feature = "no_std"))]
+use std::borrow::Cow;
+#[cfg(feature = "no_std")]
+use alloc::borrow::Cow;
+
+#[cfg(not(feature = "no_std"))]
+use std::{cmp, iter};
+#[cfg(feature = "no_std")]
+use core::{cmp, iter};
+
+#[cfg(not(feature = "no_std"))]
+use std::cell::Cell;
+#[cfg(feature = "no_std")]
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use nom::{alpha, digit, digit0, multispace, not_line_ending, Context, ErrorKind, IResult};
 use nom::types::CompleteStr;
 
-use ast::{Expr, Ident, Opcode, Stmt, StmtBlock};
+use ast::{Expr, ExprKind, Ident, Opcode, Param, ParseError, Span, Stmt, StmtBlock, StmtKind};
+
+
+// --- Errors ---
+
+/*
+ * Error codes attached to unrecoverable parser failures by the cut! macro. Each code identifies
+ * what the parser expected to find at the position of the failure.
+ */
+const ERR_ARGS_END:    u32 = 1;
+const ERR_ASSIGN:      u32 = 2;
+const ERR_BLOCK:       u32 = 3;
+const ERR_BLOCK_END:   u32 = 4;
+const ERR_CLOSE_BRACE: u32 = 5;
+const ERR_CLOSE_PAREN: u32 = 6;
+const ERR_COLON:       u32 = 7;
+const ERR_COMMENT_END: u32 = 8;
+const ERR_DEFAULT:     u32 = 9;
+const ERR_DICT_END:    u32 = 10;
+const ERR_DIGITS:      u32 = 11;
+const ERR_ELSE:        u32 = 12;
+const ERR_ESCAPE:      u32 = 13;
+const ERR_EXPR:        u32 = 14;
+const ERR_IDENT:       u32 = 15;
+const ERR_IN:          u32 = 16;
+const ERR_INDEX_END:   u32 = 17;
+const ERR_INT_RANGE:   u32 = 18;
+const ERR_LIST_END:    u32 = 19;
+const ERR_LOOP_JUMP:   u32 = 20;
+const ERR_NAMED_ARG:   u32 = 21;
+const ERR_OPEN_PAREN:  u32 = 22;
+const ERR_PARAMS_END:  u32 = 23;
+const ERR_PARAM_NAME:  u32 = 24;
+const ERR_STATEMENT:   u32 = 25;
+const ERR_STMT_END:    u32 = 26;
+const ERR_STR_END:     u32 = 27;
+
+/// Returns a description of the input that was expected when a parser failed with `code`
+fn expected_description(code: u32) -> &'static str {
+    match code {
+        ERR_ARGS_END    => "',' or ')'",
+        ERR_ASSIGN      => "'='",
+        ERR_BLOCK       => "'{'",
+        ERR_BLOCK_END   => "';' or '}'",
+        ERR_CLOSE_BRACE => "'}'",
+        ERR_CLOSE_PAREN => "')'",
+        ERR_COLON       => "':'",
+        ERR_COMMENT_END => "'*/'",
+        ERR_DEFAULT     => "parameter with a default value",
+        ERR_DICT_END    => "',' or '}'",
+        ERR_DIGITS      => "digits",
+        ERR_ELSE        => "'else'",
+        ERR_ESCAPE      => "escape sequence",
+        ERR_EXPR        => "expression",
+        ERR_IDENT       => "identifier",
+        ERR_IN          => "'in'",
+        ERR_INDEX_END   => "']'",
+        ERR_INT_RANGE   => "an integer between -9223372036854775808 and 9223372036854775807",
+        ERR_LIST_END    => "',' or ']'",
+        ERR_LOOP_JUMP   => "statement other than 'break' or 'continue' outside a loop",
+        ERR_NAMED_ARG   => "named argument",
+        ERR_OPEN_PAREN  => "'('",
+        ERR_PARAMS_END  => "',' or ')'",
+        ERR_PARAM_NAME  => "unused parameter name",
+        ERR_STMT_END    => "';' or end of input",
+        ERR_STR_END     => "'\"'",
+        _               => "statement",
+    }
+}
+
+/*
+ * cut!(code, submac!(...)): commits the parser to the current alternative.
+ *
+ * A recoverable error from the sub-parser is turned into an unrecoverable nom::Err::Failure
+ * positioned at the sub-parser's input (after any leading whitespace and comments) and tagged
+ * with `code`, so that enclosing alt!s do not try other alternatives and parse() can report the
+ * position of the error. Failures from nested cut!s are passed through unchanged as their
+ * position is more precise.
+ */
+macro_rules! cut (
+    ($i:expr, $code:expr, $submac:ident!( $($args:tt)* )) => (
+        {
+            let input = $i;
+            match $submac!(input, $($args)*) {
+                Err(::nom::Err::Error(_)) => {
+                    let pos = skip(input).map_or(input, |(rest, _)| rest);
+                    Err(::nom::Err::Failure(Context::Code(pos, ErrorKind::Custom($code))))
+                },
+                res => res,
+            }
+        }
+    );
+    ($i:expr, $code:expr, $f:expr) => (
+        cut!($i, $code, call!($f))
+    );
+);
+
+
+// --- Whitespace and comments ---
+
+/*
+ * Parses a block comment ("/* ... */"). Block comments may be nested, so each "/*" within the
+ * comment must be closed by its own "*/".
+ */
+fn block_comment(input: CompleteStr) -> IResult<CompleteStr, CompleteStr> {
+    let (mut rest, _) = tag!(input, "/*")?;
+    let mut depth = 1;
+    while depth > 0 {
+        if rest.0.starts_with("/*") {
+            depth += 1;
+            rest = CompleteStr(&rest.0[2..]);
+        } else if rest.0.starts_with("*/") {
+            depth -= 1;
+            rest = CompleteStr(&rest.0[2..]);
+        } else {
+            match rest.0.chars().next() {
+                Some(c) => rest = CompleteStr(&rest.0[c.len_utf8()..]),
+                None => {
+                    return Err(::nom::Err::Failure(Context::Code(
+                        rest,
+                        ErrorKind::Custom(ERR_COMMENT_END),
+                    )))
+                },
+            }
+        }
+    }
+    Ok((rest, CompleteStr(&input.0[..input.0.len() - rest.0.len()])))
+}
+
+// Parser for a line comment: "//" up to the end of the line
+named!(line_comment<CompleteStr, CompleteStr>,
+    recognize!(pair!(tag!("//"), not_line_ending))
+);
+
+// Parser for any amount of whitespace and comments, including none
+named!(skip<CompleteStr, CompleteStr>,
+    recognize!(many0!(alt!(multispace | line_comment | block_comment)))
+);
+
+/*
+ * Position of the last non-empty run of whitespace and comments skipped after a token by wsc!,
+ * as the number of bytes remaining in the input at its start and end. spanned! uses it to exclude
+ * trailing whitespace and comments from a Span.
+ */
+#[cfg(not(feature = "no_std"))]
+thread_local!(static TRAILING_SKIP: Cell<(usize, usize)> = const { Cell::new((0, 0)) });
+#[cfg(feature = "no_std")]
+static TRAILING_SKIP: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+#[cfg(not(feature = "no_std"))]
+fn set_trailing_skip(start: usize, end: usize) {
+    TRAILING_SKIP.with(|x| x.set((start, end)));
+}
+
+#[cfg(feature = "no_std")]
+fn set_trailing_skip(start: usize, end: usize) {
+    TRAILING_SKIP[0].store(start, Ordering::Relaxed);
+    TRAILING_SKIP[1].store(end, Ordering::Relaxed);
+}
+
+#[cfg(not(feature = "no_std"))]
+fn trailing_skip() -> (usize, usize) {
+    TRAILING_SKIP.with(Cell::get)
+}
+
+#[cfg(feature = "no_std")]
+fn trailing_skip() -> (usize, usize) {
+    (TRAILING_SKIP[0].load(Ordering::Relaxed), TRAILING_SKIP[1].load(Ordering::Relaxed))
+}
+
+// Parser for whitespace and comments following a token, recording their position if any
+fn skip_trailing(input: CompleteStr) -> IResult<CompleteStr, CompleteStr> {
+    let (rest, skipped) = skip(input)?;
+    if !skipped.0.is_empty() {
+        set_trailing_skip(input.0.len(), rest.0.len());
+    }
+    Ok((rest, skipped))
+}
+
+/*
+ * wsc!(submac!(...)): equivalent to nom's ws!, but skips comments as well as whitespace before
+ * and after each sub-parser.
+ */
+macro_rules! wsc (
+    ($i:expr, $($args:tt)*) => (
+        terminated!($i, sep!(skip, $($args)*), skip_trailing)
+    );
+);
+
+
+// --- Spans ---
+
+/*
+ * While parsing, the position of the input within the source is unknown, so Spans are recorded
+ * as the number of bytes remaining in the input at the start and end of each element. Once the
+ * AST has been built, parse() uses a SpanResolver to convert them to offsets, lines and columns.
+ *
+ * The end of a raw Span excludes any whitespace and comments that the element's parser skipped
+ * after its last token (see skip_trailing()).
+ */
+fn raw_span(start: CompleteStr, end: CompleteStr) -> Span {
+    let (skip_start, skip_end) = trailing_skip();
+    Span {
+        start: start.0.len(),
+        end:   if skip_end == end.0.len() { cmp::min(skip_start, start.0.len()) } else { end.0.len() },
+        ..Span::default()
+    }
+}
+
+/*
+ * spanned!(submac!(...)): returns the output of the sub-parser along with the raw Span of the
+ * input that it consumed.
+ */
+macro_rules! spanned (
+    ($i:expr, $submac:ident!( $($args:tt)* )) => (
+        {
+            let input = $i;
+            match $submac!(input, $($args)*) {
+                Ok((rest, o)) => Ok((rest, (o, raw_span(input, rest)))),
+                Err(e) => Err(e),
+            }
+        }
+    );
+    ($i:expr, $f:expr) => (
+        spanned!($i, call!($f))
+    );
+);
+
+// expr_node!(submac!(...)): builds an Expr from the ExprKind returned by the sub-parser
+macro_rules! expr_node (
+    ($i:expr, $submac:ident!( $($args:tt)* )) => (
+        map!($i, spanned!($submac!($($args)*)), |(kind, span)| Expr::new(kind, span))
+    );
+);
+
+// stmt_node!(submac!(...)): builds a Stmt from the StmtKind returned by the sub-parser
+macro_rules! stmt_node (
+    ($i:expr, $submac:ident!( $($args:tt)* )) => (
+        map!($i, spanned!($submac!($($args)*)), |(kind, span)| Stmt::new(kind, span))
+    );
+);
+
+/*
+ * Converts the raw Spans of an AST (see raw_span()) into byte offsets, lines and columns within
+ * the source. Resolved Spans exclude any surrounding whitespace and comments.
+ *
+ * Also records the offset of the first "break" or "continue" that is not within a loop of the
+ * same Function (`stray`), as `loops` counts the loops enclosing the Stmt being resolved.
+ */
+struct SpanResolver<'s> {
+    src:         &'s str,
+    line_starts: Vec<usize>,
+    loops:       usize,
+    stray:       Option<usize>,
+}
+
+impl<'s> SpanResolver<'s> {
+    fn new(src: &'s str) -> SpanResolver<'s> {
+        SpanResolver {
+            src,
+            line_starts: iter::once(0)
+                .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+                .collect(),
+            loops: 0,
+            stray: None,
+        }
+    }
+
+    fn span(&self, span: &mut Span) {
+        let start = self.src.len() - span.start;
+        let end   = self.src.len() - span.end;
+        let text  = &self.src[start..end];
+        let lead  = skip(CompleteStr(text)).map_or(0, |(rest, _)| text.len() - rest.0.len());
+        let start = start + lead;
+        let line  = match self.line_starts.binary_search(&start) {
+            Ok(x)  => x,
+            Err(x) => x - 1,
+        };
+        span.start  = start;
+        span.end    = cmp::max(start, end);
+        span.line   = line + 1;
+        span.column = self.src[self.line_starts[line]..start].chars().count() + 1;
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        self.span(&mut expr.span);
+        match expr.kind {
+            ExprKind::BinOp(ref mut l, _, ref mut r) => {
+                self.expr(l);
+                self.expr(r);
+            },
+            ExprKind::Dict(ref mut items) => {
+                for item in items.iter_mut() {
+                    self.expr(&mut item.1);
+                }
+            },
+            ExprKind::Call(ref mut func, ref mut exprs, ref mut named) => {
+                self.expr(func);
+                for x in exprs.iter_mut() {
+                    self.expr(x);
+                }
+                for x in named.iter_mut() {
+                    self.expr(&mut x.1);
+                }
+            },
+            ExprKind::FuncCall(_, ref mut exprs, ref mut named) => {
+                for x in exprs.iter_mut() {
+                    self.expr(x);
+                }
+                for x in named.iter_mut() {
+                    self.expr(&mut x.1);
+                }
+            },
+            ExprKind::List(ref mut exprs) => {
+                for x in exprs.iter_mut() {
+                    self.expr(x);
+                }
+            },
+            ExprKind::If(ref mut branches, ref mut else_x) => {
+                for branch in branches.iter_mut() {
+                    self.expr(&mut branch.0);
+                    self.expr(&mut branch.1);
+                }
+                self.expr(else_x);
+            },
+            ExprKind::ListElement(ref mut coll, ref mut idx) => {
+                self.expr(coll);
+                self.expr(idx);
+            },
+            ExprKind::Lambda(ref mut params, _, ref mut stmts) => {
+                self.params(params);
+                self.function(stmts);
+            },
+            ExprKind::UnaryOp(_, ref mut x) => self.expr(x),
+            ExprKind::Bool(_)
+            | ExprKind::Id(_)
+            | ExprKind::Int(_)
+            | ExprKind::None
+            | ExprKind::Real(_)
+            | ExprKind::Str(_) => {},
+        }
+    }
+
+    fn stmt(&mut self, stmt: &mut Stmt) {
+        self.span(&mut stmt.span);
+        match stmt.kind {
+            StmtKind::Break | StmtKind::Continue => {
+                if self.loops == 0 && self.stray.is_none() {
+                    self.stray = Some(stmt.span.start);
+                }
+            },
+            StmtKind::Assign(_, _, ref mut x)
+            | StmtKind::Expr(ref mut x)
+            | StmtKind::Let(_, ref mut x)
+            | StmtKind::Return(ref mut x) => self.expr(x),
+            StmtKind::Block(ref mut stmts) => self.stmts(stmts),
+            StmtKind::Loop(ref mut stmts) => self.loop_body(stmts),
+            StmtKind::FnDef(_, ref mut params, _, ref mut stmts) => {
+                self.params(params);
+                self.function(stmts);
+            },
+            StmtKind::ForRange(_, ref mut start, ref mut end, ref mut stmts) => {
+                self.expr(start);
+                self.expr(end);
+                self.loop_body(stmts);
+            },
+            StmtKind::ForIn(_, ref mut x, ref mut stmts) | StmtKind::While(ref mut x, ref mut stmts) => {
+                self.expr(x);
+                self.loop_body(stmts);
+            },
+            StmtKind::If(ref mut branches, ref mut else_stmts) => {
+                for branch in branches.iter_mut() {
+                    self.expr(&mut branch.0);
+                    self.stmts(&mut branch.1);
+                }
+                if let Some(ref mut stmts) = *else_stmts {
+                    self.stmts(stmts);
+                }
+            },
+            StmtKind::ListItemAssignment(_, ref mut path, _, ref mut val) => {
+                for idx in path.iter_mut() {
+                    self.expr(idx);
+                }
+                self.expr(val);
+            },
+        }
+    }
+
+    fn stmts(&mut self, stmts: &mut StmtBlock) {
+        for stmt in stmts.iter_mut() {
+            self.stmt(stmt);
+        }
+    }
+
+    fn loop_body(&mut self, stmts: &mut StmtBlock) {
+        self.loops += 1;
+        self.stmts(stmts);
+        self.loops -= 1;
+    }
+
+    // A Function body is not within the loops enclosing the Function
+    fn function(&mut self, stmts: &mut StmtBlock) {
+        let loops = self.loops;
+        self.loops = 0;
+        self.stmts(stmts);
+        self.loops = loops;
+    }
+
+    fn params(&mut self, params: &mut [Param]) {
+        for param in params.iter_mut() {
+            if let Some(ref mut x) = param.default {
+                self.expr(x);
+            }
+        }
+    }
+}
+
+
+/*
+ * keyword!("kw"): parses the keyword "kw", failing if it is only the start of a longer identifier
+ * (e.g. "letter" does not start with the keyword "let").
+ */
+macro_rules! keyword (
+    ($i:expr, $kw:expr) => (
+        terminated!($i, tag!($kw), ident_end)
+    );
+);
 
 /*
  * Takes an optional sign (&str, "+" or "-") and a number and returns the correct signed number
@@ -59,26 +502,64 @@ named!(real<CompleteStr, f64>,
     )
 );
 
-// Parser for a single integer number: optional number_sign followed by an integer number
-named!(int<CompleteStr, isize>,
-    do_parse!(
-        sign: opt!(number_sign) >>
-        num: map_res!(
-            digit,
-            |s: CompleteStr| s.0.parse::<isize>()
-        ) >>
-        ( signed_number(sign, num) )
-    )
-);
+/*
+ * Parses the digits of an integer in the given radix. Digits after the first may be separated by
+ * "_" characters (e.g. "1_000_000", "ff_ff").
+ *
+ * Produces None if the number does not fit in a u64.
+ */
+fn radix_digits(input: CompleteStr, radix: u32) -> IResult<CompleteStr, Option<u64>> {
+    let len = input
+        .0
+        .char_indices()
+        .find(|&(i, c)| !(c.is_digit(radix) || (c == '_' && i > 0)))
+        .map_or(input.0.len(), |(i, _)| i);
+    if len == 0 {
+        return Err(::nom::Err::Error(Context::Code(input, ErrorKind::Custom(ERR_DIGITS))));
+    }
+    let num = u64::from_str_radix(&input.0[..len].replace('_', ""), radix).ok();
+    Ok((CompleteStr(&input.0[len..]), num))
+}
+
+/*
+ * Parser for a single integer number: optional number_sign followed by an integer number in
+ * decimal, hexadecimal ("0x"), binary ("0b") or octal ("0o") form.
+ *
+ * Numbers outside of the range of i64 fail with ERR_INT_RANGE at the start of the number.
+ */
+fn int(input: CompleteStr) -> IResult<CompleteStr, i64> {
+    let (rest, (sign, num)) = pair!(
+        input,
+        opt!(number_sign),
+        alt!(
+            preceded!(tag!("0x"), cut!(ERR_DIGITS, call!(radix_digits, 16))) |
+            preceded!(tag!("0b"), cut!(ERR_DIGITS, call!(radix_digits, 2)))  |
+            preceded!(tag!("0o"), cut!(ERR_DIGITS, call!(radix_digits, 8)))  |
+            call!(radix_digits, 10)
+        )
+    )?;
+    let num = match (sign, num) {
+        // i64::MIN has no positive counterpart, so negative numbers have one extra value
+        (Some(CompleteStr("-")), Some(n)) if n <= 1 << 63 => Some((n as i64).wrapping_neg()),
+        (_, Some(n)) if n <= i64::MAX as u64 => Some(n as i64),
+        _ => None,
+    };
+    match num {
+        Some(num) => Ok((rest, num)),
+        None => Err(::nom::Err::Failure(Context::Code(input, ErrorKind::Custom(ERR_INT_RANGE)))),
+    }
+}
 
 /// Returns true if the char is valid for an identifier (not in first position)
 fn is_ident_char(c: char) -> bool {
-    match c {
-        'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => true,
-        _ => false
-    }
+    matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '_')
 }
 
+// Parser succeeding only if the input does not continue with an identifier char
+named!(ident_end<CompleteStr, ()>,
+    not!(take_while1!(is_ident_char))
+);
+
 // Parser for a single language identifier (e.g. "name1")
 named!(ident<CompleteStr, Ident>,
     map!(
@@ -90,13 +571,19 @@ named!(ident<CompleteStr, Ident>,
 
 // --- Expressions ---
 
-// Parser for logical (&&, ||, ^) Opcodes
-named!(logical_opcode<CompleteStr, Opcode>,
-    alt!(
-        map!(tag!("&&"), |_| Opcode::LogicalAnd) |
-        map!(tag!("||"), |_| Opcode::LogicalOr)  |
-        map!(tag!("^"),  |_| Opcode::LogicalXor)
-    )
+// Parser for logical or ("||") Opcode
+named!(or_opcode<CompleteStr, Opcode>,
+    map!(tag!("||"), |_| Opcode::LogicalOr)
+);
+
+// Parser for logical xor ("^") Opcode
+named!(xor_opcode<CompleteStr, Opcode>,
+    map!(tag!("^"), |_| Opcode::LogicalXor)
+);
+
+// Parser for logical and ("&&") Opcode
+named!(and_opcode<CompleteStr, Opcode>,
+    map!(tag!("&&"), |_| Opcode::LogicalAnd)
 );
 
 // Parser for relational Opcodes (e.g. <, >=, !=)
@@ -111,15 +598,43 @@ named!(relational_opcode<CompleteStr, Opcode>,
     )
 );
 
-// Parser for "*", "/", "%" Opcodes
+// Parser for bitwise and ("&") Opcode, but not the "&&" Opcode
+named!(bitand_opcode<CompleteStr, Opcode>,
+    map!(terminated!(tag!("&"), not!(char!('&'))), |_| Opcode::BitAnd)
+);
+
+// Parser for bitwise or ("|") Opcode, but not the "||" Opcode
+named!(bitor_opcode<CompleteStr, Opcode>,
+    map!(terminated!(tag!("|"), not!(char!('|'))), |_| Opcode::BitOr)
+);
+
+// Parser for "<<", ">>" Opcodes
+named!(shift_opcode<CompleteStr, Opcode>,
+    alt!(
+        map!(tag!("<<"), |_| Opcode::Shl) |
+        map!(tag!(">>"), |_| Opcode::Shr)
+    )
+);
+
+/*
+ * Parser for "*", "/", "~/", "%" Opcodes
+ *
+ * Integer (floor) division is spelled "~/" as "//" begins a line comment.
+ */
 named!(product_opcode<CompleteStr, Opcode>,
     alt!(
+        map!(tag!("~/"), |_| Opcode::FloorDiv) |
         map!(tag!("*"), |_| Opcode::Mul) |
         map!(tag!("/"), |_| Opcode::Div) |
         map!(tag!("%"), |_| Opcode::Mod)
     )
 );
 
+// Parser for "**" Opcode
+named!(power_opcode<CompleteStr, Opcode>,
+    map!(tag!("**"), |_| Opcode::Pow)
+);
+
 // Parser for "+", "-" Opcodes
 named!(sum_opcode<CompleteStr, Opcode>,
     alt!(
@@ -128,90 +643,271 @@ named!(sum_opcode<CompleteStr, Opcode>,
     )
 );
 
-// Parser for an expression term: parses either an "expr" delimited by
-// parentheses (recursion) or another language value type
-named!(term<CompleteStr, Expr>,
+/*
+ * Parser for a single postfix index: either "[expr]" or a member access (".key"), which is
+ * equivalent to "[\"key\"]"
+ */
+named!(index_suffix<CompleteStr, Expr>,
     alt!(
-        ws!(delimited!(tag!("("), expr, tag!(")"))) |
-        ws!(value_expr)
+        delimited!(
+            wsc!(tag!("[")),
+            cut!(ERR_EXPR, expr),
+            cut!(ERR_INDEX_END, wsc!(tag!("]")))
+        ) |
+        preceded!(wsc!(tag!(".")), expr_node!(map!(ident, |x| ExprKind::Str(Cow::Borrowed(x)))))
+    )
+);
+
+// Parser for a positional call argument: any expr that does not begin a named argument
+named!(positional_arg<CompleteStr, Expr>,
+    preceded!(not!(terminated!(ident, wsc!(tag!(":")))), expr)
+);
+
+// Parser for a named call argument (e.g. "port: 80")
+named!(named_arg<CompleteStr, (Ident, Expr)>,
+    do_parse!(
+        id: terminated!(ident, wsc!(tag!(":"))) >>
+        val: cut!(ERR_EXPR, expr) >>
+        (id, val)
+    )
+);
+
+// Parser for one or more named call arguments. Only named arguments may follow a named argument.
+named!(named_args<CompleteStr, Vec<(Ident, Expr)>>,
+    do_parse!(
+        first: named_arg >>
+        rest: many0!(preceded!(wsc!(tag!(",")), cut!(ERR_NAMED_ARG, named_arg))) >>
+        ( iter::once(first).chain(rest).collect() )
+    )
+);
+
+/*
+ * Parser for a parenthesised list of call arguments: any positional arguments followed by any
+ * named arguments (e.g. "(1, a + 2, port: 80)")
+ */
+named!(call_args<CompleteStr, CallArgs>,
+    delimited!(
+        wsc!(tag!("(")),
+        alt!(
+            pair!(
+                separated_nonempty_list!(wsc!(tag!(",")), positional_arg),
+                map!(opt!(preceded!(wsc!(tag!(",")), named_args)), Option::unwrap_or_default)
+            ) |
+            map!(named_args, |x| (vec![], x)) |
+            value!((vec![], vec![]))
+        ),
+        cut!(ERR_ARGS_END, wsc!(tag!(")")))
     )
 );
 
-// Parser for logical expressions (e.g. true && false)
-named!(logical_expr<CompleteStr, Expr>,
+// Positional and named arguments of a call (see call_args), and the same with boxed Exprs as held
+// by ExprKind::Call and ExprKind::FuncCall
+type CallArgs<'src> = (Vec<Expr<'src>>, Vec<(Ident<'src>, Expr<'src>)>);
+type BoxedCallArgs<'src> = (Vec<Box<Expr<'src>>>, Vec<(Ident<'src>, Box<Expr<'src>>)>);
+
+// Boxes the argument Exprs of a call
+fn boxed_args((args, named): CallArgs) -> BoxedCallArgs {
+    (
+        args.into_iter().map(Box::new).collect(),
+        named.into_iter().map(|(id, x)| (id, Box::new(x))).collect(),
+    )
+}
+
+// Postfix operation following a term: an index (see index_suffix) or a call with arguments
+enum Postfix<'src> {
+    Call(CallArgs<'src>),
+    Index(Expr<'src>),
+}
+
+// Parser for a single postfix operation
+named!(postfix<CompleteStr, Postfix>,
     alt!(
-        do_parse!(
-            lhs: term >>
-            op:  ws!(logical_opcode) >>
-            rhs: logical_expr >>
-            ( Expr::BinOp(Box::new(lhs), op, Box::new(rhs)) )
-        ) |
-        term
+        map!(index_suffix, Postfix::Index) |
+        map!(call_args,    Postfix::Call)
+    )
+);
+
+/*
+ * Folds a postfix operation onto an accumulated expression.
+ *
+ * Used with fold_many0! so that chains such as "a[1].b(2)" are built as nested ListElements and
+ * Calls: (((a[1])["b"])(2)).
+ */
+fn fold_postfix<'src>(lhs: Expr<'src>, (op, span): (Postfix<'src>, Span)) -> Expr<'src> {
+    let span = lhs.span.to(&span);
+    let kind = match op {
+        Postfix::Call(args) => {
+            let (args, named) = boxed_args(args);
+            ExprKind::Call(Box::new(lhs), args, named)
+        },
+        Postfix::Index(idx) => ExprKind::ListElement(Box::new(lhs), Box::new(idx)),
+    };
+    Expr::new(kind, span)
+}
+
+// Parser for an expression term: parses either an "expr" delimited by
+// parentheses (recursion) or another language value type, followed by any number of postfix
+// indexes and calls
+named!(term<CompleteStr, Expr>,
+    do_parse!(
+        init: alt!(
+            wsc!(map!(
+                spanned!(delimited!(tag!("("), cut!(ERR_EXPR, expr), cut!(ERR_CLOSE_PAREN, tag!(")")))),
+                |(x, span)| Expr::new(x.kind, span)
+            )) |
+            wsc!(value_expr)
+        ) >>
+        res: fold_many0!(spanned!(postfix), init, fold_postfix) >>
+        ( res )
+    )
+);
+
+/*
+ * Folds a binary operation onto an accumulated left-hand side expression.
+ *
+ * Used with fold_many0! by each binary operator level so that chains such as "1 - 2 - 3" are
+ * built as left-associative BinOps: ((1 - 2) - 3).
+ */
+fn fold_binop<'src>(lhs: Expr<'src>, (op, rhs): (Opcode, Expr<'src>)) -> Expr<'src> {
+    let span = lhs.span.to(&rhs.span);
+    Expr::new(ExprKind::BinOp(Box::new(lhs), op, Box::new(rhs)), span)
+}
+
+/*
+ * Binary operator precedence, from loosest to tightest binding: -
+ *
+ *   - or_expr:         ||
+ *   - xor_expr:        ^
+ *   - and_expr:        &&
+ *   - relational_expr: ==, !=, <, >, <=, >=
+ *   - bitor_expr:      |
+ *   - bitand_expr:     &
+ *   - shift_expr:      <<, >>
+ *   - sum_expr:        +, -
+ *   - product_expr:    *, /, ~/, %
+ *   - power_expr:      ** (right-associative)
+ *   - term:            unary operators, literals, identifiers, calls and parenthesised exprs,
+ *                      each followed by any postfix indexes
+ *
+ * Every binary level other than power_expr is left-associative.
+ */
+
+// Parser for logical or expressions (e.g. true || false)
+named!(or_expr<CompleteStr, Expr>,
+    do_parse!(
+        init: xor_expr >>
+        res:  fold_many0!(pair!(wsc!(or_opcode), cut!(ERR_EXPR, xor_expr)), init, fold_binop) >>
+        ( res )
+    )
+);
+
+// Parser for logical xor expressions (e.g. true ^ false)
+named!(xor_expr<CompleteStr, Expr>,
+    do_parse!(
+        init: and_expr >>
+        res:  fold_many0!(pair!(wsc!(xor_opcode), cut!(ERR_EXPR, and_expr)), init, fold_binop) >>
+        ( res )
+    )
+);
+
+// Parser for logical and expressions (e.g. true && false)
+named!(and_expr<CompleteStr, Expr>,
+    do_parse!(
+        init: relational_expr >>
+        res:  fold_many0!(pair!(wsc!(and_opcode), cut!(ERR_EXPR, relational_expr)), init, fold_binop) >>
+        ( res )
     )
 );
 
 // Parser for relational expressions (e.g. 1 < 2)
 named!(relational_expr<CompleteStr, Expr>,
-    alt!(
-        do_parse!(
-            lhs: logical_expr >>
-            op:  ws!(relational_opcode) >>
-            rhs: relational_expr >>
-            ( Expr::BinOp(Box::new(lhs), op, Box::new(rhs)) )
-        ) |
-        logical_expr
+    do_parse!(
+        init: bitor_expr >>
+        res:  fold_many0!(pair!(wsc!(relational_opcode), cut!(ERR_EXPR, bitor_expr)), init, fold_binop) >>
+        ( res )
     )
 );
 
-// Parser for product expressions (e.g. 2 * 3)
-named!(product_expr<CompleteStr, Expr>,
-    alt!(
-        do_parse!(
-            lhs: relational_expr >>
-            op:  ws!(product_opcode) >>
-            rhs: product_expr >>
-            ( Expr::BinOp(Box::new(lhs), op, Box::new(rhs)) )
-        ) |
-        relational_expr
+// Parser for bitwise or expressions (e.g. 1 | 2)
+named!(bitor_expr<CompleteStr, Expr>,
+    do_parse!(
+        init: bitand_expr >>
+        res:  fold_many0!(pair!(wsc!(bitor_opcode), cut!(ERR_EXPR, bitand_expr)), init, fold_binop) >>
+        ( res )
+    )
+);
+
+// Parser for bitwise and expressions (e.g. 1 & 2)
+named!(bitand_expr<CompleteStr, Expr>,
+    do_parse!(
+        init: shift_expr >>
+        res:  fold_many0!(pair!(wsc!(bitand_opcode), cut!(ERR_EXPR, shift_expr)), init, fold_binop) >>
+        ( res )
+    )
+);
+
+// Parser for shift expressions (e.g. 1 << 2)
+named!(shift_expr<CompleteStr, Expr>,
+    do_parse!(
+        init: sum_expr >>
+        res:  fold_many0!(pair!(wsc!(shift_opcode), cut!(ERR_EXPR, sum_expr)), init, fold_binop) >>
+        ( res )
     )
 );
 
 // Parser for sum expressions (e.g. 1 + 2)
 named!(sum_expr<CompleteStr, Expr>,
-    alt!(
-        do_parse!(
-            lhs: product_expr >>
-            op:  ws!(sum_opcode) >>
-            rhs: sum_expr >>
-            ( Expr::BinOp(Box::new(lhs), op, Box::new(rhs)) )
-        ) |
-        product_expr
+    do_parse!(
+        init: product_expr >>
+        res:  fold_many0!(pair!(wsc!(sum_opcode), cut!(ERR_EXPR, product_expr)), init, fold_binop) >>
+        ( res )
+    )
+);
+
+// Parser for product expressions (e.g. 2 * 3)
+named!(product_expr<CompleteStr, Expr>,
+    do_parse!(
+        init: power_expr >>
+        res:  fold_many0!(pair!(wsc!(product_opcode), cut!(ERR_EXPR, power_expr)), init, fold_binop) >>
+        ( res )
+    )
+);
+
+// Parser for power expressions (e.g. 2 ** 3), which are right-associative: 2 ** 3 ** 2 is 2 ** 9
+named!(power_expr<CompleteStr, Expr>,
+    do_parse!(
+        base: term >>
+        exp:  opt!(pair!(wsc!(power_opcode), cut!(ERR_EXPR, power_expr))) >>
+        ( match exp {
+            Some(exp) => fold_binop(base, exp),
+            None      => base,
+        } )
     )
 );
 
 // Parser for any language expression
 named!(expr<CompleteStr, Expr>,
-    call!(sum_expr)
+    call!(or_expr)
 );
 
 // Parser for Boolean literals
 named!(bool_literal<CompleteStr, bool>,
     alt!(
-        tag_no_case!("true") => { |_| true } |
-        tag_no_case!("false") => { |_| false }
+        terminated!(tag_no_case!("true"),  ident_end) => { |_| true } |
+        terminated!(tag_no_case!("false"), ident_end) => { |_| false }
     )
 );
 
 // Parser for Dict literals
 named!(dict_literal<CompleteStr, Expr>,
-    map!(
+    expr_node!(map!(
         delimited!(
-            ws!(tag!("{")),
-            separated_list!(ws!(tag!(",")), map!(key_val_pair, |(k, v)| (k, Box::new(v)))),
-            ws!(tag!("}"))
+            wsc!(tag!("{")),
+            separated_list!(wsc!(tag!(",")), map!(key_val_pair, |(k, v)| (k, Box::new(v)))),
+            cut!(ERR_DICT_END, wsc!(tag!("}")))
         ),
-        Expr::Dict
-    )
+        ExprKind::Dict
+    ))
 );
 
 // Parser for float literals (calls real)
@@ -219,193 +915,430 @@ named!(float_literal<CompleteStr, f64>, call!(real));
 
 // Parser for function call expressions
 named!(func_call<CompleteStr, Expr>,
-    do_parse!(
+    expr_node!(do_parse!(
         id: ident >>
-        args: delimited!(
-            ws!(tag!("(")),
-            separated_list!(ws!(tag!(",")), map!(expr, Box::new)),
-            ws!(tag!(")"))
-        ) >>
-        ( Expr::FuncCall(id, args) )
-    )
+        args: map!(call_args, boxed_args) >>
+        ( ExprKind::FuncCall(id, args.0, args.1) )
+    ))
+);
+
+// Parser for anonymous Function (lambda) expressions (e.g. "fn(a, b) { return a + b; }")
+named!(lambda<CompleteStr, Expr>,
+    expr_node!(do_parse!(
+        keyword!("fn") >>
+        params: preceded!(peek!(wsc!(tag!("("))), params) >>
+        stmts: cut!(ERR_BLOCK, statement_block) >>
+        ( ExprKind::Lambda(params.0, params.1, stmts) )
+    ))
 );
 
 // Parser for int literals
-named!(int_literal<CompleteStr, isize>,
+named!(int_literal<CompleteStr, i64>,
    call!(int)
 );
 
 // Parser for a key (string) / value (expr) pair
-named!(key_val_pair<CompleteStr, (Ident, Expr)>,
+named!(key_val_pair<CompleteStr, (Cow<str>, Expr)>,
     do_parse!(
         key: str_literal >>
-        ws!(tag!(":")) >>
-        val: expr >>
+        cut!(ERR_COLON, wsc!(tag!(":"))) >>
+        val: cut!(ERR_EXPR, expr) >>
         (key, val)
     )
 );
 
-// Parser for list elements: list identifier and index
-named!(list_element<CompleteStr, Expr>,
-    do_parse!(
-        id: ident >>
-        idx: delimited!(
-            ws!(tag!("[")),
-            map!(expr, Box::new),
-            ws!(tag!("]"))
-        ) >>
-        ( Expr::ListElement(id, idx) )
-    )
-);
-
 // Parser for a List literal
 named!(list_literal<CompleteStr, Expr>,
-    map!(
+    expr_node!(map!(
         delimited!(
-            ws!(tag!("[")),
-            separated_list!(ws!(tag!(",")), map!(expr, Box::new)),
-            ws!(tag!("]"))
+            wsc!(tag!("[")),
+            separated_list!(wsc!(tag!(",")), map!(expr, Box::new)),
+            cut!(ERR_LIST_END, wsc!(tag!("]")))
         ),
-        Expr::List
-    )
+        ExprKind::List
+    ))
 );
 
-// Parser for string literals (characters enclosed by '"' characters)
-named!(str_literal<CompleteStr, &str>,
-    alt!(
-        map!(
-            delimited!(char!('"'), is_not!("\""), char!('"')),
-            |x: CompleteStr| x.0
-        ) |
-        map!(tag!(r#""""#), |_| "")
-    )
-);
+/*
+ * Parses the escape sequence following a "\" within a string literal, returning the char that it
+ * represents and its length in bytes. Supported sequences are \n, \t, \", \\ and \u{X} where X is
+ * 1 to 6 hex digits giving a Unicode scalar value.
+ */
+fn escape_sequence(s: &str) -> Option<(char, usize)> {
+    match s.chars().next()? {
+        'n'  => Some(('\n', 1)),
+        't'  => Some(('\t', 1)),
+        '"'  => Some(('"', 1)),
+        '\\' => Some(('\\', 1)),
+        'u'  => {
+            let digits = &s[2..2 + s.get(2..)?.find('}')?];
+            if !s[1..].starts_with('{')
+                || digits.is_empty()
+                || digits.len() > 6
+                || !digits.chars().all(|c| c.is_ascii_hexdigit())
+            {
+                return None;
+            }
+            let c = char::from_u32(u32::from_str_radix(digits, 16).ok()?)?;
+            Some((c, digits.len() + 3))
+        },
+        _ => None,
+    }
+}
+
+/*
+ * Parser for string literals (characters enclosed by '"' characters)
+ *
+ * Escape sequences are replaced by the chars that they represent (see escape_sequence()). The
+ * resulting string borrows from the source unless the literal contains an escape sequence.
+ */
+fn str_literal(input: CompleteStr) -> IResult<CompleteStr, Cow<str>> {
+    let (rest, _) = char!(input, '"')?;
+    let text = rest.0;
+
+    // Text is only copied into `owned` once an escape sequence is found
+    let mut owned: Option<String> = None;
+    let mut run_start = 0;
+    let mut pos = 0;
+    loop {
+        match text[pos..].chars().next() {
+            Some('"') => {
+                let res = match owned {
+                    Some(mut s) => {
+                        s.push_str(&text[run_start..pos]);
+                        Cow::Owned(s)
+                    },
+                    None => Cow::Borrowed(&text[..pos]),
+                };
+                return Ok((CompleteStr(&text[pos + 1..]), res));
+            },
+            Some('\\') => match escape_sequence(&text[pos + 1..]) {
+                Some((c, len)) => {
+                    let s = owned.get_or_insert_with(String::new);
+                    s.push_str(&text[run_start..pos]);
+                    s.push(c);
+                    pos += 1 + len;
+                    run_start = pos;
+                },
+                None => {
+                    return Err(::nom::Err::Failure(Context::Code(
+                        CompleteStr(&text[pos..]),
+                        ErrorKind::Custom(ERR_ESCAPE),
+                    )))
+                },
+            },
+            Some(c) => pos += c.len_utf8(),
+            None => {
+                return Err(::nom::Err::Failure(Context::Code(
+                    CompleteStr(&text[pos..]),
+                    ErrorKind::Custom(ERR_STR_END),
+                )))
+            },
+        }
+    }
+}
 
-// Parser for a unary Opcode (e.g. "!")
+// Parser for a unary Opcode (e.g. "!", "-")
 named!(unary_opcode<CompleteStr, Opcode>,
     alt!(
-        tag!("!") => { |_| Opcode::Not }
+        tag!("!") => { |_| Opcode::Not }    |
+        tag!("-") => { |_| Opcode::Neg }    |
+        tag!("+") => { |_| Opcode::Pos }    |
+        tag!("~") => { |_| Opcode::BitNot }
     )
 );
 
-// Parser for any unary operation (e.g. "!true")
+// Parser for any unary operation (e.g. "!true"): the operand is a single term, so unary
+// operators bind more tightly than any binary operator
 named!(unary_op<CompleteStr, Expr>,
-    do_parse!(
+    expr_node!(do_parse!(
         op: unary_opcode >>
-        t:  expr >>
-        ( Expr::UnaryOp(op, Box::new(t)) )
+        t:  cut!(ERR_EXPR, term) >>
+        ( ExprKind::UnaryOp(op, Box::new(t)) )
+    ))
+);
+
+// Parser for a single expression enclosed within braces, used for the branches of if_expr
+named!(expr_block<CompleteStr, Expr>,
+    delimited!(
+        wsc!(tag!("{")),
+        cut!(ERR_EXPR, expr),
+        cut!(ERR_CLOSE_BRACE, wsc!(tag!("}")))
     )
 );
 
+/*
+ * Parser for an "if" expression (e.g. "if a { 1 } else if b { 2 } else { 3 }"). Each branch is a
+ * single expression and the "else" branch is required so that the expression always has a value.
+ */
+named!(if_expr<CompleteStr, Expr>,
+    expr_node!(do_parse!(
+        wsc!(keyword!("if")) >>
+        cond: cut!(ERR_EXPR, expr) >>
+        val: cut!(ERR_BLOCK, expr_block) >>
+        branches: many0!(do_parse!(
+            wsc!(keyword!("else")) >>
+            wsc!(keyword!("if")) >>
+            cond: cut!(ERR_EXPR, expr) >>
+            val: cut!(ERR_BLOCK, expr_block) >>
+            ( (cond, val) )
+        )) >>
+        cut!(ERR_ELSE, wsc!(keyword!("else"))) >>
+        else_val: cut!(ERR_BLOCK, expr_block) >>
+        ( ExprKind::If(iter::once((cond, val)).chain(branches).collect(), Box::new(else_val)) )
+    ))
+);
+
 // Parser for any language expression that results in a single value
 named!(value_expr<CompleteStr, Expr>,
     alt!(
-        map!(float_literal,     Expr::Real) |
-        map!(int_literal,       Expr::Int)  |
-        map!(bool_literal,      Expr::Bool) |
-        map!(str_literal,       Expr::Str)  |
-        map!(tag!("null"),  |_| Expr::None) |
-        func_call                           |
-        dict_literal                        |
-        list_literal                        |
-        list_element                        |
-        unary_op                            |
-        map!(ident,             Expr::Id)
+        expr_node!(map!(float_literal,       ExprKind::Real)) |
+        expr_node!(map!(int_literal,         ExprKind::Int))  |
+        expr_node!(map!(bool_literal,        ExprKind::Bool)) |
+        expr_node!(map!(str_literal,         ExprKind::Str))  |
+        expr_node!(map!(keyword!("null"), |_| ExprKind::None)) |
+        if_expr                                               |
+        lambda                                                |
+        func_call                                             |
+        dict_literal                                          |
+        list_literal                                          |
+        unary_op                                              |
+        expr_node!(map!(ident,               ExprKind::Id))
     )
 );
 
 
 // --- Statements ---
 
+// Parser for the assignment operator "=" (but not the "==" Opcode)
+named!(assign_op<CompleteStr, CompleteStr>,
+    terminated!(tag!("="), not!(char!('=')))
+);
+
+/*
+ * Parser for plain ("=") and compound ("+=", "-=", "*=", "/=", "%=") assignment operators,
+ * returning the Opcode applied by a compound assignment
+ */
+named!(assignment_opcode<CompleteStr, Option<Opcode>>,
+    alt!(
+        value!(None,                assign_op) |
+        value!(Some(Opcode::Add),   tag!("+=")) |
+        value!(Some(Opcode::Sub),   tag!("-=")) |
+        value!(Some(Opcode::Mul),   tag!("*=")) |
+        value!(Some(Opcode::Div),   tag!("/=")) |
+        value!(Some(Opcode::Mod),   tag!("%="))
+    )
+);
+
+// Parser for an assignment to an existing variable ("x = expr", "x += expr", etc.)
+named!(assignment_statement<CompleteStr, Stmt>,
+    stmt_node!(do_parse!(
+        id: wsc!(ident) >>
+        opc: wsc!(assignment_opcode) >>
+        val: cut!(ERR_EXPR, wsc!(expr)) >>
+        ( StmtKind::Assign(id, opc, val) )
+    ))
+);
+
+/*
+ * Parser for a bare block of statements ("{ ... }"). As a statement, a "{" always begins a block
+ * rather than a dict literal.
+ */
+named!(block_statement<CompleteStr, Stmt>,
+    stmt_node!(map!(statement_block, StmtKind::Block))
+);
+
 named!(break_statement<CompleteStr, Stmt>,
-    map!(ws!(tag!("break")), |_| Stmt::Break)
+    wsc!(stmt_node!(map!(keyword!("break"), |_| StmtKind::Break)))
+);
+
+named!(continue_statement<CompleteStr, Stmt>,
+    wsc!(stmt_node!(map!(keyword!("continue"), |_| StmtKind::Continue)))
 );
 
 named!(expr_statement<CompleteStr, Stmt>,
-    map!(expr, Stmt::Expr)
+    map!(expr, |x| {
+        let span = x.span;
+        Stmt::new(StmtKind::Expr(x), span)
+    })
 );
 
-named!(fndef_statement<CompleteStr, Stmt>,
+// Parser for a Function parameter name, optionally followed by a default value (e.g. "b = 2")
+named!(param<CompleteStr, Param>,
     do_parse!(
-        ws!(tag!("fn")) >>
         id: ident >>
-        args: delimited!(
-            ws!(tag!("(")),
-            separated_list!(ws!(tag!(",")), ident),
-            ws!(tag!(")"))
-        ) >>
-        stmts: statement_block >>
-        ( Stmt::FnDef(id, args, stmts) )
+        default: opt!(preceded!(wsc!(tag!("=")), cut!(ERR_EXPR, expr))) >>
+        ( Param { id, default } )
     )
 );
 
-named!(if_statement<CompleteStr, Stmt>,
-    do_parse!(
-        ws!(tag!("if")) >>
-        cond: expr >>
-        stmts: statement_block >>
-        ( Stmt::If(cond, stmts) )
-    )
+// Parser returning its input without consuming any, used to record the position of a parameter
+fn position(input: CompleteStr) -> IResult<CompleteStr, CompleteStr> {
+    Ok((input, input))
+}
+
+// Parser for a Function parameter along with its position
+named!(located_param<CompleteStr, (CompleteStr, Param)>,
+    pair!(position, param)
 );
 
-named!(if_else_statement<CompleteStr, Stmt>,
-    do_parse!(
-        ws!(tag!("if")) >>
-        cond: expr >>
-        stmts_t: statement_block >>
-        ws!(tag!("else")) >>
-        stmts_f: statement_block >>
-        ( Stmt::IfElse(cond, stmts_t, stmts_f) )
-    )
+// Parser for a rest parameter (e.g. "...rest") along with the position of its Ident
+named!(rest_param<CompleteStr, (CompleteStr, Ident)>,
+    preceded!(wsc!(tag!("...")), pair!(position, cut!(ERR_IDENT, ident)))
+);
+
+/*
+ * Parser for a parenthesised list of Function parameters, the last of which may be a rest
+ * parameter (e.g. "(a, b = 2, ...rest)")
+ *
+ * Fails with ERR_PARAM_NAME at a parameter with the same name as an earlier one, and with
+ * ERR_DEFAULT at a parameter without a default value following one with a default value.
+ */
+fn params(input: CompleteStr) -> IResult<CompleteStr, (Vec<Param>, Option<Ident>)> {
+    let (rest, (params, rest_param)) = delimited!(
+        input,
+        cut!(ERR_OPEN_PAREN, wsc!(tag!("("))),
+        alt!(
+            map!(rest_param, |x| (vec![], Some(x))) |
+            pair!(
+                separated_nonempty_list!(wsc!(tag!(",")), located_param),
+                opt!(preceded!(wsc!(tag!(",")), rest_param))
+            ) |
+            value!((vec![], None))
+        ),
+        cut!(ERR_PARAMS_END, wsc!(tag!(")")))
+    )?;
+    let fail = |pos, code| Err(::nom::Err::Failure(Context::Code(pos, ErrorKind::Custom(code))));
+    let ids = params.iter().map(|x| (x.0, x.1.id)).chain(rest_param).collect::<Vec<_>>();
+    for (i, &(pos, id)) in ids.iter().enumerate() {
+        if ids[..i].iter().any(|x| x.1 == id) {
+            return fail(pos, ERR_PARAM_NAME);
+        }
+    }
+    for (i, &(pos, ref param)) in params.iter().enumerate() {
+        if param.default.is_none() && params[..i].iter().any(|x| x.1.default.is_some()) {
+            return fail(pos, ERR_DEFAULT);
+        }
+    }
+    Ok((rest, (params.into_iter().map(|x| x.1).collect(), rest_param.map(|x| x.1))))
+}
+
+// Parser for a named Function definition. "fn" followed by "(" begins a lambda instead.
+named!(fndef_statement<CompleteStr, Stmt>,
+    stmt_node!(do_parse!(
+        wsc!(keyword!("fn")) >>
+        not!(tag!("(")) >>
+        id: cut!(ERR_IDENT, ident) >>
+        params: params >>
+        stmts: cut!(ERR_BLOCK, statement_block) >>
+        ( StmtKind::FnDef(id, params.0, params.1, stmts) )
+    ))
+);
+
+/*
+ * Parser for a "for" loop over the items of a collection ("for x in list { ... }") or over a
+ * half-open range of integers ("for i in 0..10 { ... }")
+ */
+named!(for_statement<CompleteStr, Stmt>,
+    stmt_node!(do_parse!(
+        wsc!(keyword!("for")) >>
+        id: cut!(ERR_IDENT, ident) >>
+        cut!(ERR_IN, wsc!(keyword!("in"))) >>
+        iter: cut!(ERR_EXPR, expr) >>
+        end: opt!(preceded!(wsc!(tag!("..")), cut!(ERR_EXPR, expr))) >>
+        stmts: cut!(ERR_BLOCK, statement_block) >>
+        ( match end {
+            Some(end) => StmtKind::ForRange(id, iter, end, stmts),
+            None      => StmtKind::ForIn(id, iter, stmts),
+        } )
+    ))
+);
+
+/*
+ * Parser for an "if" statement with any number of "else if" branches and an optional "else"
+ * branch. All conditional branches are collected into a single list in order.
+ */
+named!(if_statement<CompleteStr, Stmt>,
+    stmt_node!(do_parse!(
+        wsc!(keyword!("if")) >>
+        cond: cut!(ERR_EXPR, expr) >>
+        stmts: cut!(ERR_BLOCK, statement_block) >>
+        branches: many0!(do_parse!(
+            wsc!(keyword!("else")) >>
+            wsc!(keyword!("if")) >>
+            cond: cut!(ERR_EXPR, expr) >>
+            stmts: cut!(ERR_BLOCK, statement_block) >>
+            ( (cond, stmts) )
+        )) >>
+        else_stmts: opt!(preceded!(wsc!(keyword!("else")), cut!(ERR_BLOCK, statement_block))) >>
+        ( StmtKind::If(iter::once((cond, stmts)).chain(branches).collect(), else_stmts) )
+    ))
 );
 
 named!(let_statement<CompleteStr, Stmt>,
-    do_parse!(
-        ws!(tag!("let")) >>
-        id: ident >>
-        ws!(tag!("=")) >> 
-        val: ws!(expr) >>
-        ( Stmt::Let(id, val) )
-    )
+    stmt_node!(do_parse!(
+        wsc!(keyword!("let")) >>
+        id: cut!(ERR_IDENT, ident) >>
+        cut!(ERR_ASSIGN, wsc!(assign_op)) >>
+        val: cut!(ERR_EXPR, wsc!(expr)) >>
+        ( StmtKind::Let(id, val) )
+    ))
 );
 
+// Parser for an assignment to an item of a collection variable (e.g. "a[1].b = expr")
 named!(list_assignment_statement<CompleteStr, Stmt>,
-    do_parse!(
-        id: ident >>
-        idx: delimited!(ws!(tag!("[")), expr, ws!(tag!("]"))) >>
-        ws!(tag!("=")) >>
-        val: ws!(expr) >>
-        ( Stmt::ListItemAssignment(id, idx, val) )
-    )
+    stmt_node!(do_parse!(
+        id: wsc!(ident) >>
+        path: many1!(index_suffix) >>
+        opc: wsc!(assignment_opcode) >>
+        val: cut!(ERR_EXPR, wsc!(expr)) >>
+        ( StmtKind::ListItemAssignment(id, path, opc, val) )
+    ))
 );
 
 named!(loop_statement<CompleteStr, Stmt>,
-    do_parse!(
-        ws!(tag!("loop")) >>
-        stmts: statement_block >>
-        ( Stmt::Loop(stmts) )
-    )
+    stmt_node!(do_parse!(
+        wsc!(keyword!("loop")) >>
+        stmts: cut!(ERR_BLOCK, statement_block) >>
+        ( StmtKind::Loop(stmts) )
+    ))
 );
 
 named!(return_statement<CompleteStr, Stmt>,
-    do_parse!(
-        ws!(tag!("return")) >>
-        val: ws!(expr) >>
-        ( Stmt::Return(val) )
-    )
+    stmt_node!(do_parse!(
+        wsc!(keyword!("return")) >>
+        val: cut!(ERR_EXPR, wsc!(expr)) >>
+        ( StmtKind::Return(val) )
+    ))
+);
+
+named!(while_statement<CompleteStr, Stmt>,
+    stmt_node!(do_parse!(
+        wsc!(keyword!("while")) >>
+        cond: cut!(ERR_EXPR, expr) >>
+        stmts: cut!(ERR_BLOCK, statement_block) >>
+        ( StmtKind::While(cond, stmts) )
+    ))
 );
 
 // Parser for a single supported statement of any type
 named!(statement<CompleteStr, Stmt>,
     alt!(
+        block_statement           |
         break_statement           |
+        continue_statement        |
         fndef_statement           |
-        if_else_statement         |
+        for_statement             |
         if_statement              |
         let_statement             |
-        list_assignment_statement |
         loop_statement            |
         return_statement          |
+        while_statement           |
+
+        // Statements beginning with an arbitrary identifier or expression are tried last so that
+        // keywords (e.g. "let") are not parsed as identifiers
+        assignment_statement      |
+        list_assignment_statement |
         expr_statement
     )
 );
@@ -413,7 +1346,7 @@ named!(statement<CompleteStr, Stmt>,
 // Parser for a list of "statement" separated by ";" with an optional trailing ";"
 named!(statements<CompleteStr, Vec<Stmt>>,
     do_parse!(
-        list: separated_list!(ws!(tag!(";")), statement) >>
+        list: separated_list!(wsc!(tag!(";")), statement) >>
         opt!(tag!(";")) >>
         ( list )
     )
@@ -421,30 +1354,59 @@ named!(statements<CompleteStr, Vec<Stmt>>,
 
 // Parser for "statements" enclosed within braces
 named!(statement_block<CompleteStr, StmtBlock>,
-    delimited!(ws!(tag!("{")), statements, ws!(tag!("}")))
+    delimited!(wsc!(tag!("{")), statements, cut!(ERR_BLOCK_END, wsc!(tag!("}"))))
 );
 
 
-// Axiom rule: parses an entire program
+/*
+ * Axiom rule: parses an entire program
+ *
+ * All input must be consumed. Trailing input is reported as an unexpected statement if it follows
+ * a ";" (or is the only input), otherwise as a missing statement separator.
+ */
 named!(program_parser<CompleteStr, StmtBlock>,
-    call!(statements)
+    do_parse!(
+        list: separated_list!(wsc!(tag!(";")), statement) >>
+        sep:  opt!(wsc!(tag!(";"))) >>
+        skip >>
+        cut!(if list.is_empty() || sep.is_some() { ERR_STATEMENT } else { ERR_STMT_END }, eof!()) >>
+        ( list )
+    )
 );
 
 /**
  * Main parser function: takes source code and returns a Result containing either the AST or a
- * string error.
+ * ParseError describing the position of the error and what was expected there.
  */
-pub fn parse<'s>(source: &'s str) -> Result<StmtBlock, &'static str> {
-    // TODO: obtain error from Nom
+pub fn parse(source: &str) -> Result<StmtBlock<'_>, ParseError<'_>> {
+    set_trailing_skip(0, 0);
     match program_parser(CompleteStr(source)) {
-        Ok((_, stmts)) => Ok(stmts),
-        Err(_) => Err("Unable to parse source"),
+        Ok((_, mut stmts)) => {
+            let mut resolver = SpanResolver::new(source);
+            resolver.stmts(&mut stmts);
+            match resolver.stray {
+                Some(offset) => Err(ParseError::new(source, offset, expected_description(ERR_LOOP_JUMP))),
+                None => Ok(stmts),
+            }
+        },
+        Err(::nom::Err::Error(Context::Code(rest, kind)))
+        | Err(::nom::Err::Failure(Context::Code(rest, kind))) => {
+            let expected = match kind {
+                ErrorKind::Custom(code) => expected_description(code),
+                _ => expected_description(ERR_STATEMENT),
+            };
+            Err(ParseError::new(source, source.len() - rest.0.len(), expected))
+        },
+        Err(::nom::Err::Incomplete(_)) => {
+            Err(ParseError::new(source, source.len(), expected_description(ERR_STATEMENT)))
+        },
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ast::StripSpans;
 
     #[test]
     fn number_sign_test_valid() {
@@ -482,6 +1444,38 @@ mod tests {
         assert_eq!(Ok((CompleteStr(""), -123)), int(CompleteStr("-123")));
 
         assert_eq!(Ok((CompleteStr(".45"), 123)), int(CompleteStr("123.45")));
+
+        assert_eq!(Ok((CompleteStr(""),  1000000)), int(CompleteStr("1_000_000")));
+        assert_eq!(Ok((CompleteStr(""),  0xFFFF)),  int(CompleteStr("0xFF_ff")));
+        assert_eq!(Ok((CompleteStr(""), -0b1010)),  int(CompleteStr("-0b1010")));
+        assert_eq!(Ok((CompleteStr(""),  0o17)),    int(CompleteStr("0o17")));
+        assert_eq!(Ok((CompleteStr("2"), 0b1)),     int(CompleteStr("0b12")));
+        assert_eq!(Ok((CompleteStr(""),  0)),       int(CompleteStr("0")));
+
+        assert_eq!(Ok((CompleteStr(""), i64::MAX)), int(CompleteStr("9223372036854775807")));
+        assert_eq!(Ok((CompleteStr(""), i64::MIN)), int(CompleteStr("-9223372036854775808")));
+        assert_eq!(Ok((CompleteStr(""), i64::MIN)), int(CompleteStr("-0x8000_0000_0000_0000")));
+    }
+
+    #[test]
+    fn int_test_invalid() {
+        assert!(int(CompleteStr("_1")).is_err());
+        assert_eq!(
+            Err(::nom::Err::Failure(Context::Code(CompleteStr(";"), ErrorKind::Custom(ERR_DIGITS)))),
+            int(CompleteStr("0x;"))
+        );
+        assert_eq!(
+            Err(::nom::Err::Failure(Context::Code(CompleteStr("_1"), ErrorKind::Custom(ERR_DIGITS)))),
+            int(CompleteStr("0b_1"))
+        );
+
+        // Out of range of i64
+        for src in ["9223372036854775808", "-9223372036854775809", "+0x8000_0000_0000_0000", "99999999999999999999999"].iter() {
+            assert_eq!(
+                Err(::nom::Err::Failure(Context::Code(CompleteStr(src), ErrorKind::Custom(ERR_INT_RANGE)))),
+                int(CompleteStr(src))
+            );
+        }
     }
 
     #[test]
@@ -500,9 +1494,9 @@ mod tests {
 
     #[test]
     fn logical_opcode_test_valid() {
-        assert_eq!(Ok((CompleteStr(""), Opcode::LogicalAnd)), logical_opcode(CompleteStr("&&")));
-        assert_eq!(Ok((CompleteStr(""), Opcode::LogicalOr)),  logical_opcode(CompleteStr("||")));
-        assert_eq!(Ok((CompleteStr(""), Opcode::LogicalXor)), logical_opcode(CompleteStr("^")));
+        assert_eq!(Ok((CompleteStr(""), Opcode::LogicalAnd)), and_opcode(CompleteStr("&&")));
+        assert_eq!(Ok((CompleteStr(""), Opcode::LogicalOr)),  or_opcode(CompleteStr("||")));
+        assert_eq!(Ok((CompleteStr(""), Opcode::LogicalXor)), xor_opcode(CompleteStr("^")));
     }
 
     #[test]
@@ -529,6 +1523,17 @@ mod tests {
         assert_eq!(Ok((CompleteStr(""), Opcode::NotEqual)),           relational_opcode(CompleteStr("!=")));
     }
 
+    #[test]
+    fn bitwise_opcode_test_valid() {
+        assert_eq!(Ok((CompleteStr(""), Opcode::BitAnd)), bitand_opcode(CompleteStr("&")));
+        assert_eq!(Ok((CompleteStr(""), Opcode::BitOr)),  bitor_opcode(CompleteStr("|")));
+        assert_eq!(Ok((CompleteStr(""), Opcode::Shl)),    shift_opcode(CompleteStr("<<")));
+        assert_eq!(Ok((CompleteStr(""), Opcode::Shr)),    shift_opcode(CompleteStr(">>")));
+
+        assert!(bitand_opcode(CompleteStr("&&")).is_err());
+        assert!(bitor_opcode(CompleteStr("||")).is_err());
+    }
+
     #[test]
     fn sum_opcode_test_valid() {
         assert_eq!(Ok((CompleteStr(""), Opcode::Add)), sum_opcode(CompleteStr("+")));
@@ -545,142 +1550,397 @@ mod tests {
     #[test]
     fn logical_expr_test_valid() {
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::LogicalAnd, Box::new(Expr::Int(2))))),
-            logical_expr(CompleteStr("1 && 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::LogicalAnd, Box::new(ExprKind::Int(2).into())).into())),
+            or_expr(CompleteStr("1 && 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::LogicalOr, Box::new(Expr::Int(2))))),
-            logical_expr(CompleteStr("1 || 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::LogicalOr, Box::new(ExprKind::Int(2).into())).into())),
+            or_expr(CompleteStr("1 || 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::LogicalXor, Box::new(Expr::Int(2))))),
-            logical_expr(CompleteStr("1 ^ 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::LogicalXor, Box::new(ExprKind::Int(2).into())).into())),
+            or_expr(CompleteStr("1 ^ 2")).strip_spans()
         );
     }
 
     #[test]
     fn relational_expr_test_valid() {
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::LessThan, Box::new(Expr::Int(2))))),
-            relational_expr(CompleteStr("1 < 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::LessThan, Box::new(ExprKind::Int(2).into())).into())),
+            relational_expr(CompleteStr("1 < 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::GreaterThan, Box::new(Expr::Int(2))))),
-            relational_expr(CompleteStr("1 > 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::GreaterThan, Box::new(ExprKind::Int(2).into())).into())),
+            relational_expr(CompleteStr("1 > 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::LessThanOrEqual, Box::new(Expr::Int(2))))),
-            relational_expr(CompleteStr("1 <= 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::LessThanOrEqual, Box::new(ExprKind::Int(2).into())).into())),
+            relational_expr(CompleteStr("1 <= 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::GreaterThanOrEqual, Box::new(Expr::Int(2))))),
-            relational_expr(CompleteStr("1 >= 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::GreaterThanOrEqual, Box::new(ExprKind::Int(2).into())).into())),
+            relational_expr(CompleteStr("1 >= 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Equal, Box::new(Expr::Int(2))))),
-            relational_expr(CompleteStr("1 == 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Equal, Box::new(ExprKind::Int(2).into())).into())),
+            relational_expr(CompleteStr("1 == 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::NotEqual, Box::new(Expr::Int(2))))),
-            relational_expr(CompleteStr("1 != 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::NotEqual, Box::new(ExprKind::Int(2).into())).into())),
+            relational_expr(CompleteStr("1 != 2")).strip_spans()
         );
     }
 
     #[test]
     fn product_expr_test_valid() {
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Mul, Box::new(Expr::Int(2))))),
-            product_expr(CompleteStr("1*2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Mul, Box::new(ExprKind::Int(2).into())).into())),
+            product_expr(CompleteStr("1*2")).strip_spans()
+        );
+        assert_eq!(
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Mul, Box::new(ExprKind::Int(2).into())).into())),
+            product_expr(CompleteStr("1 *2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Mul, Box::new(Expr::Int(2))))),
-            product_expr(CompleteStr("1 *2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Mul, Box::new(ExprKind::Int(2).into())).into())),
+            product_expr(CompleteStr("1* 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Mul, Box::new(Expr::Int(2))))),
-            product_expr(CompleteStr("1* 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Mul, Box::new(ExprKind::Int(2).into())).into())),
+            product_expr(CompleteStr("1 * 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Mul, Box::new(Expr::Int(2))))),
-            product_expr(CompleteStr("1 * 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Real(1.23f64).into()), Opcode::Div, Box::new(ExprKind::Int(2).into())).into())),
+            product_expr(CompleteStr("1.23 / 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Real(1.23f64)), Opcode::Div, Box::new(Expr::Int(2))))),
-            product_expr(CompleteStr("1.23 / 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Mod, Box::new(ExprKind::Int(2).into())).into())),
+            product_expr(CompleteStr("1 % 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Mod, Box::new(Expr::Int(2))))),
-            product_expr(CompleteStr("1 % 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(7).into()), Opcode::FloorDiv, Box::new(ExprKind::Int(2).into())).into())),
+            product_expr(CompleteStr("7 ~/ 2")).strip_spans()
         );
+        assert_eq!(
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(2).into()), Opcode::Pow, Box::new(ExprKind::Int(3).into())).into())),
+            product_expr(CompleteStr("2**3")).strip_spans()
+        );
+
+        // "//" is a line comment rather than an Opcode
+        assert_eq!(Ok((CompleteStr(""), ExprKind::Int(7).into())), product_expr(CompleteStr("7 // 2")).strip_spans());
     }
 
     #[test]
     fn sum_expr_test_valid() {
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Add, Box::new(Expr::Int(2))))),
-            sum_expr(CompleteStr("1+2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Add, Box::new(ExprKind::Int(2).into())).into())),
+            sum_expr(CompleteStr("1+2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Add, Box::new(Expr::Int(2))))),
-            sum_expr(CompleteStr("1 +2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Add, Box::new(ExprKind::Int(2).into())).into())),
+            sum_expr(CompleteStr("1 +2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Add, Box::new(Expr::Int(2))))),
-            sum_expr(CompleteStr("1+ 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Add, Box::new(ExprKind::Int(2).into())).into())),
+            sum_expr(CompleteStr("1+ 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Add, Box::new(Expr::Int(2))))),
-            sum_expr(CompleteStr("1 + 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Add, Box::new(ExprKind::Int(2).into())).into())),
+            sum_expr(CompleteStr("1 + 2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Real(1.23f64)), Opcode::Sub, Box::new(Expr::Int(2))))),
-            sum_expr(CompleteStr("1.23 - 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Real(1.23f64).into()), Opcode::Sub, Box::new(ExprKind::Int(2).into())).into())),
+            sum_expr(CompleteStr("1.23 - 2")).strip_spans()
         );
     }
 
     #[test]
     fn expr_valid() {
-        assert_eq!(Ok((CompleteStr(""), Expr::Real(1.23f64))), expr(CompleteStr("1.23")));
+        assert_eq!(Ok((CompleteStr(""), ExprKind::Real(1.23f64).into())), expr(CompleteStr("1.23")).strip_spans());
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Add, Box::new(Expr::Int(2))))),
-            expr(CompleteStr("1+2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Add, Box::new(ExprKind::Int(2).into())).into())),
+            expr(CompleteStr("1+2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Mul, Box::new(Expr::Int(2))))),
-            expr(CompleteStr("1*2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Mul, Box::new(ExprKind::Int(2).into())).into())),
+            expr(CompleteStr("1*2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::LessThan, Box::new(Expr::Int(2))))),
-            expr(CompleteStr("1<2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::LessThan, Box::new(ExprKind::Int(2).into())).into())),
+            expr(CompleteStr("1<2")).strip_spans()
         );
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::LogicalOr, Box::new(Expr::Int(2))))),
-            expr(CompleteStr("1 || 2"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::LogicalOr, Box::new(ExprKind::Int(2).into())).into())),
+            expr(CompleteStr("1 || 2")).strip_spans()
         );
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Expr::BinOp(
-                    Box::new(Expr::Real(1.23f64)),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::Real(1.23f64).into()),
                     Opcode::Add,
-                    Box::new(Expr::BinOp(
-                        Box::new(Expr::Real(2.34f64)),
+                    Box::new(ExprKind::BinOp(
+                        Box::new(ExprKind::Real(2.34f64).into()),
                         Opcode::Mul,
-                        Box::new(Expr::Real(3.45f64)),
-                    ))
-                )
+                        Box::new(ExprKind::Real(3.45f64).into()),
+                    ).into())
+                ).into()
+            )),
+            expr(CompleteStr("1.23 + 2.34 * 3.45")).strip_spans()
+        );
+    }
+
+    #[test]
+    fn expr_associativity_valid() {
+        // ((1 - 2) - 3)
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Sub, Box::new(ExprKind::Int(2).into())).into()),
+                    Opcode::Sub,
+                    Box::new(ExprKind::Int(3).into())
+                ).into()
+            )),
+            expr(CompleteStr("1 - 2 - 3")).strip_spans()
+        );
+
+        // ((8 / 4) * 2)
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Int(8).into()), Opcode::Div, Box::new(ExprKind::Int(4).into())).into()),
+                    Opcode::Mul,
+                    Box::new(ExprKind::Int(2).into())
+                ).into()
+            )),
+            expr(CompleteStr("8 / 4 * 2")).strip_spans()
+        );
+
+        // (2 ** (3 ** 2))
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::Int(2).into()),
+                    Opcode::Pow,
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Int(3).into()), Opcode::Pow, Box::new(ExprKind::Int(2).into())).into())
+                ).into()
+            )),
+            expr(CompleteStr("2 ** 3 ** 2")).strip_spans()
+        );
+
+        // ((1 < 2) == true)
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::LessThan, Box::new(ExprKind::Int(2).into())).into()),
+                    Opcode::Equal,
+                    Box::new(ExprKind::Bool(true).into())
+                ).into()
+            )),
+            expr(CompleteStr("1 < 2 == true")).strip_spans()
+        );
+
+        // ((a && b) || c)
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Id("a").into()), Opcode::LogicalAnd, Box::new(ExprKind::Id("b").into())).into()),
+                    Opcode::LogicalOr,
+                    Box::new(ExprKind::Id("c").into())
+                ).into()
+            )),
+            expr(CompleteStr("a && b || c")).strip_spans()
+        );
+
+        // (a || (b && c))
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::Id("a").into()),
+                    Opcode::LogicalOr,
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Id("b").into()), Opcode::LogicalAnd, Box::new(ExprKind::Id("c").into())).into())
+                ).into()
+            )),
+            expr(CompleteStr("a || b && c")).strip_spans()
+        );
+
+        // (a || (b ^ (c && d)))
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::Id("a").into()),
+                    Opcode::LogicalOr,
+                    Box::new(ExprKind::BinOp(
+                        Box::new(ExprKind::Id("b").into()),
+                        Opcode::LogicalXor,
+                        Box::new(ExprKind::BinOp(Box::new(ExprKind::Id("c").into()), Opcode::LogicalAnd, Box::new(ExprKind::Id("d").into())).into())
+                    ).into())
+                ).into()
+            )),
+            expr(CompleteStr("a || b ^ c && d")).strip_spans()
+        );
+    }
+
+    #[test]
+    fn expr_precedence_valid() {
+        // (1 + 2) < 4
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Add, Box::new(ExprKind::Int(2).into())).into()),
+                    Opcode::LessThan,
+                    Box::new(ExprKind::Int(4).into())
+                ).into()
+            )),
+            expr(CompleteStr("1 + 2 < 4")).strip_spans()
+        );
+
+        // ((a & b) == c) && (d | (e << 1))
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::BinOp(
+                        Box::new(ExprKind::BinOp(Box::new(ExprKind::Id("a").into()), Opcode::BitAnd, Box::new(ExprKind::Id("b").into())).into()),
+                        Opcode::Equal,
+                        Box::new(ExprKind::Id("c").into())
+                    ).into()),
+                    Opcode::LogicalAnd,
+                    Box::new(ExprKind::BinOp(
+                        Box::new(ExprKind::Id("d").into()),
+                        Opcode::BitOr,
+                        Box::new(ExprKind::BinOp(Box::new(ExprKind::Id("e").into()), Opcode::Shl, Box::new(ExprKind::Int(1).into())).into())
+                    ).into())
+                ).into()
+            )),
+            expr(CompleteStr("a & b == c && d | e << 1")).strip_spans()
+        );
+
+        // (1 << (2 + 3)) | (4 & 5)
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::BinOp(
+                        Box::new(ExprKind::Int(1).into()),
+                        Opcode::Shl,
+                        Box::new(ExprKind::BinOp(Box::new(ExprKind::Int(2).into()), Opcode::Add, Box::new(ExprKind::Int(3).into())).into())
+                    ).into()),
+                    Opcode::BitOr,
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Int(4).into()), Opcode::BitAnd, Box::new(ExprKind::Int(5).into())).into())
+                ).into()
+            )),
+            expr(CompleteStr("1 << 2 + 3 | 4 & 5")).strip_spans()
+        );
+
+        // (a * 2) + (b % 3)
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Id("a").into()), Opcode::Mul, Box::new(ExprKind::Int(2).into())).into()),
+                    Opcode::Add,
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Id("b").into()), Opcode::Mod, Box::new(ExprKind::Int(3).into())).into())
+                ).into()
+            )),
+            expr(CompleteStr("a * 2 + b % 3")).strip_spans()
+        );
+
+        // (a * (b ** 2)) ~/ c
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::BinOp(
+                        Box::new(ExprKind::Id("a").into()),
+                        Opcode::Mul,
+                        Box::new(ExprKind::BinOp(Box::new(ExprKind::Id("b").into()), Opcode::Pow, Box::new(ExprKind::Int(2).into())).into())
+                    ).into()),
+                    Opcode::FloorDiv,
+                    Box::new(ExprKind::Id("c").into())
+                ).into()
+            )),
+            expr(CompleteStr("a * b ** 2 ~/ c")).strip_spans()
+        );
+
+        // (a < 1) && (b >= 2)
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Id("a").into()), Opcode::LessThan, Box::new(ExprKind::Int(1).into())).into()),
+                    Opcode::LogicalAnd,
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Id("b").into()), Opcode::GreaterThanOrEqual, Box::new(ExprKind::Int(2).into())).into())
+                ).into()
+            )),
+            expr(CompleteStr("a < 1 && b >= 2")).strip_spans()
+        );
+
+        // (!a) && b
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::UnaryOp(Opcode::Not, Box::new(ExprKind::Id("a").into())).into()),
+                    Opcode::LogicalAnd,
+                    Box::new(ExprKind::Id("b").into())
+                ).into()
+            )),
+            expr(CompleteStr("!a && b")).strip_spans()
+        );
+
+        // (-a) * b - (-c[0])
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::BinOp(
+                        Box::new(ExprKind::UnaryOp(Opcode::Neg, Box::new(ExprKind::Id("a").into())).into()),
+                        Opcode::Mul,
+                        Box::new(ExprKind::Id("b").into())
+                    ).into()),
+                    Opcode::Sub,
+                    Box::new(ExprKind::UnaryOp(
+                        Opcode::Neg,
+                        Box::new(ExprKind::ListElement(
+                            Box::new(ExprKind::Id("c").into()),
+                            Box::new(ExprKind::Int(0).into())
+                        ).into())
+                    ).into())
+                ).into()
+            )),
+            expr(CompleteStr("-a * b - -c[0]")).strip_spans()
+        );
+
+        // 1 * (2 + 3)
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::Int(1).into()),
+                    Opcode::Mul,
+                    Box::new(ExprKind::BinOp(Box::new(ExprKind::Int(2).into()), Opcode::Add, Box::new(ExprKind::Int(3).into())).into())
+                ).into()
             )),
-            expr(CompleteStr("1.23 + 2.34 * 3.45"))
+            expr(CompleteStr("1 * (2 + 3)")).strip_spans()
         );
     }
 
     #[test]
     fn term_valid() {
         assert_eq!(
-            Ok((CompleteStr(""), Expr::BinOp(Box::new(Expr::Int(1)), Opcode::Add, Box::new(Expr::Int(2))))),
-            term(CompleteStr("(1+2)"))
+            Ok((CompleteStr(""), ExprKind::BinOp(Box::new(ExprKind::Int(1).into()), Opcode::Add, Box::new(ExprKind::Int(2).into())).into())),
+            term(CompleteStr("(1+2)")).strip_spans()
         );
-        assert_eq!(Ok((CompleteStr(""), Expr::Real(1.23f64))), term(CompleteStr("1.23")));
+        assert_eq!(Ok((CompleteStr(""), ExprKind::Real(1.23f64).into())), term(CompleteStr("1.23")).strip_spans());
     }
 
     #[test]
@@ -698,12 +1958,12 @@ mod tests {
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Expr::Dict(vec![
-                   ("a",   Box::new(Expr::Int(1))),
-                   ("bcd", Box::new(Expr::Real(23.45f64)))
-                ])
+                ExprKind::Dict(vec![
+                   ("a".into(),   Box::new(ExprKind::Int(1).into())),
+                   ("bcd".into(), Box::new(ExprKind::Real(23.45f64).into()))
+                ]).into()
             )),
-            dict_literal(CompleteStr(r#"{"a":1,"bcd":23.45}"#))
+            dict_literal(CompleteStr(r#"{"a":1,"bcd":23.45}"#)).strip_spans()
         );
     }
 
@@ -713,20 +1973,143 @@ mod tests {
     }
 
     #[test]
-    fn func_call_valid() {
+    fn func_call_valid() {
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::FuncCall(
+                    "testFun",
+                    vec![
+                        Box::new(ExprKind::Int(1).into()),
+                        Box::new(ExprKind::Int(2).into()),
+                        Box::new(ExprKind::Int(3).into()),
+                    ],
+                    vec![]
+                ).into()
+            )),
+            func_call(CompleteStr("testFun(1, 2, 3)")).strip_spans()
+        );
+
+        // Named arguments, alone or following positional arguments
+        let int = |x| Box::new(ExprKind::Int(x).into());
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::FuncCall(
+                    "connect",
+                    vec![],
+                    vec![("host", Box::new(ExprKind::Str("x".into()).into())), ("port", int(80))]
+                ).into()
+            )),
+            func_call(CompleteStr(r#"connect(host: "x", port : 80)"#)).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::FuncCall(
+                    "f",
+                    vec![int(1), Box::new(ExprKind::Id("a").into())],
+                    vec![("b", int(2))]
+                ).into()
+            )),
+            func_call(CompleteStr("f(1, a, b: 2)")).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::Call(Box::new(ExprKind::Id("f").into()), vec![], vec![("a", int(1))]).into()
+            )),
+            expr(CompleteStr("(f)(a: 1)")).strip_spans()
+        );
+
+        // Positional arguments cannot follow named arguments
+        for (src, code) in [("f(a: 1, 2)", ERR_NAMED_ARG), ("f(a: )", ERR_EXPR)].iter() {
+            match func_call(CompleteStr(src)) {
+                Err(::nom::Err::Failure(Context::Code(_, ErrorKind::Custom(x)))) => assert_eq!(*code, x, "{}", src),
+                x => panic!("func_call(): {} should fail, not {:?}", src, x),
+            }
+        }
+    }
+
+    #[test]
+    fn call_valid() {
+        let int = |x| Box::new(ExprKind::Int(x).into());
+
+        // ((f(1))(2))
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::Call(Box::new(ExprKind::FuncCall("f", vec![int(1)], vec![]).into()), vec![int(2)], vec![]).into()
+            )),
+            expr(CompleteStr("f(1)(2)")).strip_spans()
+        );
+
+        // ((a[0])())
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::Call(
+                    Box::new(ExprKind::ListElement(Box::new(ExprKind::Id("a").into()), int(0)).into()),
+                    vec![],
+                    vec![]
+                ).into()
+            )),
+            expr(CompleteStr("a[0]()")).strip_spans()
+        );
+
+        // (fn(x) { return x; })(3)
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::Call(
+                    Box::new(ExprKind::Lambda(
+                        vec!["x".into()],
+                        None,
+                        vec![StmtKind::Return(ExprKind::Id("x").into()).into()]
+                    ).into()),
+                    vec![int(3)],
+                    vec![]
+                ).into()
+            )),
+            expr(CompleteStr("(fn(x) { return x; })(3)")).strip_spans()
+        );
+
+        match expr(CompleteStr("f(1)(2")) {
+            Err(::nom::Err::Failure(Context::Code(rest, ErrorKind::Custom(ERR_ARGS_END)))) => {
+                assert_eq!(CompleteStr(""), rest)
+            },
+            x => panic!("expr(): unterminated call should fail, not {:?}", x),
+        }
+    }
+
+    #[test]
+    fn lambda_valid() {
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Expr::FuncCall(
-                    "testFun",
-                    vec![
-                        Box::new(Expr::Int(1)),
-                        Box::new(Expr::Int(2)),
-                        Box::new(Expr::Int(3)),
-                    ]
-                )
+                ExprKind::Lambda(
+                    vec!["a".into(), "b".into()],
+                    None,
+                    vec![StmtKind::Return(
+                        ExprKind::BinOp(Box::new(ExprKind::Id("a").into()), Opcode::Add, Box::new(ExprKind::Id("b").into())).into()
+                    ).into()]
+                ).into()
             )),
-            func_call(CompleteStr("testFun(1, 2, 3)"))
+            value_expr(CompleteStr("fn (a, b) { return a + b; }")).strip_spans()
+        );
+        assert_eq!(
+            Ok((CompleteStr(""), ExprKind::Lambda(vec![], None, vec![]).into())),
+            value_expr(CompleteStr("fn() {}")).strip_spans()
+        );
+
+        // A lambda at the start of a statement is an expression rather than a named Function
+        assert_eq!(
+            Ok(vec![StmtKind::Expr(ExprKind::Call(Box::new(ExprKind::Lambda(vec![], None, vec![]).into()), vec![], vec![]).into()).into()]),
+            parse("fn() {}();").strip_spans()
+        );
+        assert_eq!(
+            Ok((CompleteStr(""), ExprKind::Id("fn").into())),
+            value_expr(CompleteStr("fn")).strip_spans()
         );
     }
     
@@ -735,27 +2118,101 @@ mod tests {
         assert_eq!(Ok((CompleteStr(""), 123)),  int_literal(CompleteStr("123")));
         assert_eq!(Ok((CompleteStr(""), 123)),  int_literal(CompleteStr("+123")));
         assert_eq!(Ok((CompleteStr(""), -123)), int_literal(CompleteStr("-123")));
+        assert_eq!(Ok((CompleteStr(""), 255)),  int_literal(CompleteStr("0xff")));
     }
 
     #[test]
     fn key_val_pair_valid() {
-        assert_eq!(Ok((CompleteStr(""), ("a", Expr::Int(1)))), key_val_pair(CompleteStr(r#""a":1"#)));
-        assert_eq!(Ok((CompleteStr(""), ("a", Expr::Int(1)))), key_val_pair(CompleteStr(r#""a" :1"#)));
-        assert_eq!(Ok((CompleteStr(""), ("a", Expr::Int(1)))), key_val_pair(CompleteStr(r#""a": 1"#)));
-        assert_eq!(Ok((CompleteStr(""), ("a", Expr::Int(1)))), key_val_pair(CompleteStr(r#""a" : 1"#)));
+        assert_eq!(Ok((CompleteStr(""), ("a".into(), ExprKind::Int(1).into()))), key_val_pair(CompleteStr(r#""a":1"#)).strip_spans());
+        assert_eq!(Ok((CompleteStr(""), ("a".into(), ExprKind::Int(1).into()))), key_val_pair(CompleteStr(r#""a" :1"#)).strip_spans());
+        assert_eq!(Ok((CompleteStr(""), ("a".into(), ExprKind::Int(1).into()))), key_val_pair(CompleteStr(r#""a": 1"#)).strip_spans());
+        assert_eq!(Ok((CompleteStr(""), ("a".into(), ExprKind::Int(1).into()))), key_val_pair(CompleteStr(r#""a" : 1"#)).strip_spans());
 
         assert_eq!(
-            Ok((CompleteStr(""), ("abc", Expr::Str("def")))),
-            key_val_pair(CompleteStr(r#""abc":"def""#))
+            Ok((CompleteStr(""), ("abc".into(), ExprKind::Str("def".into()).into()))),
+            key_val_pair(CompleteStr(r#""abc":"def""#)).strip_spans()
         );
     }
 
     #[test]
     fn list_element_valid() {
+        let elem = |coll: Expr<'static>, idx: Expr<'static>| -> Expr<'static> {
+            ExprKind::ListElement(Box::new(coll), Box::new(idx)).into()
+        };
+        let id = |x| -> Expr { ExprKind::Id(x).into() };
+        assert_eq!(
+            Ok((CompleteStr(""), elem(id("a"), ExprKind::Int(1).into()))),
+            term(CompleteStr("a[1]")).strip_spans()
+        );
+
+        // Chains of indexes and member accesses
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                elem(elem(id("grid"), id("y")), id("x"))
+            )),
+            term(CompleteStr("grid[y] [x]")).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                elem(
+                    elem(elem(id("cfg"), ExprKind::Str("net".into()).into()), ExprKind::Int(0).into()),
+                    ExprKind::Str("port".into()).into()
+                )
+            )),
+            term(CompleteStr("cfg.net[0].port")).strip_spans()
+        );
+
+        // Any term can be indexed, including calls, literals and parenthesised exprs
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                elem(ExprKind::FuncCall("f", vec![], vec![]).into(), ExprKind::Int(0).into())
+            )),
+            term(CompleteStr("f()[0]")).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                elem(
+                    ExprKind::List(vec![Box::new(ExprKind::Int(1).into())]).into(),
+                    ExprKind::Int(0).into()
+                )
+            )),
+            term(CompleteStr("[1][0]")).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                elem(id("a"), ExprKind::Str("b".into()).into())
+            )),
+            term(CompleteStr("(a).b")).strip_spans()
+        );
+
+        // Indexes bind more tightly than unary operators, and ".." is not a member access
         assert_eq!(
-            Ok((CompleteStr(""), Expr::ListElement("a", Box::new(Expr::Int(1))))),
-            list_element(CompleteStr("a[1]"))
+            Ok((
+                CompleteStr(""),
+                ExprKind::UnaryOp(
+                    Opcode::Not,
+                    Box::new(elem(id("a"), ExprKind::Str("b".into()).into()))
+                ).into()
+            )),
+            term(CompleteStr("!a.b")).strip_spans()
         );
+        assert_eq!(Ok((CompleteStr("..b"), id("a"))), term(CompleteStr("a..b")).strip_spans());
+    }
+
+    #[test]
+    fn list_element_invalid() {
+        let expected = |src| match parse(src) {
+            Err(e) => e.expected,
+            Ok(x) => panic!("parse(): {}: should fail, not {:?}", src, x),
+        };
+        assert_eq!("expression", expected("return a[];"));
+        assert_eq!("']'",        expected("return a[1;"));
+        assert_eq!("']'",        expected("return a[1][2;"));
     }
 
     #[test]
@@ -763,116 +2220,173 @@ mod tests {
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Expr::List(vec![
-                   Box::new(Expr::Int(1)),
-                   Box::new(Expr::Str("two")),
-                   Box::new(Expr::Bool(true)),
-                   Box::new(Expr::Real(4.56f64)),
-                ])
+                ExprKind::List(vec![
+                   Box::new(ExprKind::Int(1).into()),
+                   Box::new(ExprKind::Str("two".into()).into()),
+                   Box::new(ExprKind::Bool(true).into()),
+                   Box::new(ExprKind::Real(4.56f64).into()),
+                ]).into()
             )),
-            list_literal(CompleteStr(r#"[1, "two", true, 4.56]"#))
+            list_literal(CompleteStr(r#"[1, "two", true, 4.56]"#)).strip_spans()
         );
     }
 
     #[test]
     fn str_literal_valid() {
-        assert_eq!(Ok((CompleteStr(""), "")),        str_literal(CompleteStr(r#""""#)));
-        assert_eq!(Ok((CompleteStr(""), "a")),       str_literal(CompleteStr(r#""a""#)));
-        assert_eq!(Ok((CompleteStr(""), "abc")),     str_literal(CompleteStr(r#""abc""#)));
-        assert_eq!(Ok((CompleteStr(""), "abc 123")), str_literal(CompleteStr(r#""abc 123""#)));
+        assert_eq!(Ok((CompleteStr(""), "".into())),        str_literal(CompleteStr(r#""""#)));
+        assert_eq!(Ok((CompleteStr(""), "a".into())),       str_literal(CompleteStr(r#""a""#)));
+        assert_eq!(Ok((CompleteStr(""), "abc".into())),     str_literal(CompleteStr(r#""abc""#)));
+        assert_eq!(Ok((CompleteStr(""), "abc 123".into())), str_literal(CompleteStr(r#""abc 123""#)));
+
+        // Escape sequences
+        assert_eq!(Ok((CompleteStr(""), "a\"b".into())), str_literal(CompleteStr(r#""a\"b""#)));
+        assert_eq!(
+            Ok((CompleteStr(" c"), "\n\t\\ \u{e9}\u{1F600}".into())),
+            str_literal(CompleteStr(r#""\n\t\\ \u{e9}\u{1F600}" c"#))
+        );
+        assert_eq!(Ok((CompleteStr(""), "a\nb".into())), str_literal(CompleteStr("\"a\nb\"")));
+
+        // Text is only copied if the literal contains escape sequences
+        match str_literal(CompleteStr(r#""abc""#)) {
+            Ok((_, Cow::Borrowed(_))) => {},
+            x => panic!("str_literal(): should borrow, not {:?}", x),
+        };
+        match str_literal(CompleteStr(r#""a\tc""#)) {
+            Ok((_, Cow::Owned(_))) => {},
+            x => panic!("str_literal(): should own, not {:?}", x),
+        };
+    }
+
+    #[test]
+    fn str_literal_invalid() {
+        for src in [r#""\q""#, r#""\u{}""#, r#""\u{1234567}""#, r#""\u{d800}""#, r#""\u{+41}""#, r#""\u41""#].iter() {
+            match str_literal(CompleteStr(src)) {
+                Err(::nom::Err::Failure(Context::Code(pos, ErrorKind::Custom(ERR_ESCAPE)))) => {
+                    assert_eq!(&src[src.find('\\').unwrap()..], pos.0);
+                },
+                x => panic!("str_literal(): {}: should fail, not {:?}", src, x),
+            };
+        }
+        match str_literal(CompleteStr(r#""abc\""#)) {
+            Err(::nom::Err::Failure(Context::Code(_, ErrorKind::Custom(ERR_STR_END)))) => {},
+            x => panic!("str_literal(): unterminated string should fail, not {:?}", x),
+        };
     }
 
     #[test]
     fn unary_opcode_valid() {
-        assert_eq!(Ok((CompleteStr(""), Opcode::Not)), unary_opcode(CompleteStr("!")));
+        assert_eq!(Ok((CompleteStr(""), Opcode::Not)),    unary_opcode(CompleteStr("!")));
+        assert_eq!(Ok((CompleteStr(""), Opcode::Neg)),    unary_opcode(CompleteStr("-")));
+        assert_eq!(Ok((CompleteStr(""), Opcode::Pos)),    unary_opcode(CompleteStr("+")));
+        assert_eq!(Ok((CompleteStr(""), Opcode::BitNot)), unary_opcode(CompleteStr("~")));
     }
 
     #[test]
     fn unary_op_valid() {
         assert_eq!(
-            Ok((CompleteStr(""), Expr::UnaryOp(Opcode::Not, Box::new(Expr::Id("a"))))),
-            unary_op(CompleteStr("!a"))
+            Ok((CompleteStr(""), ExprKind::UnaryOp(Opcode::Not, Box::new(ExprKind::Id("a").into())).into())),
+            unary_op(CompleteStr("!a")).strip_spans()
+        );
+        assert_eq!(
+            Ok((CompleteStr(""), ExprKind::UnaryOp(Opcode::Not, Box::new(ExprKind::Bool(true).into())).into())),
+            unary_op(CompleteStr("!true")).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::UnaryOp(
+                    Opcode::Neg,
+                    Box::new(ExprKind::UnaryOp(Opcode::BitNot, Box::new(ExprKind::Id("a").into())).into())
+                ).into()
+            )),
+            unary_op(CompleteStr("- ~a")).strip_spans()
         );
+
+        // Signed numeric literals are not unary operations
+        assert_eq!(Ok((CompleteStr(""), ExprKind::Int(-1).into())), value_expr(CompleteStr("-1")).strip_spans());
         assert_eq!(
-            Ok((CompleteStr(""), Expr::UnaryOp(Opcode::Not, Box::new(Expr::Bool(true))))),
-            unary_op(CompleteStr("!true"))
+            Ok((CompleteStr(""), ExprKind::UnaryOp(Opcode::Neg, Box::new(ExprKind::Int(-1).into())).into())),
+            unary_op(CompleteStr("--1")).strip_spans()
         );
     }
 
     #[test]
     fn value_expr_valid() {
-        assert_eq!(Ok((CompleteStr(""), Expr::Real(1.23f64))),          value_expr(CompleteStr("1.23")));
-        assert_eq!(Ok((CompleteStr(""), Expr::Int(123))),               value_expr(CompleteStr("123")));
-        assert_eq!(Ok((CompleteStr(""), Expr::Bool(true))),             value_expr(CompleteStr("true")));
-        assert_eq!(Ok((CompleteStr(""), Expr::Str("abc"))), value_expr(CompleteStr(r#""abc""#)));
-        assert_eq!(Ok((CompleteStr(""), Expr::None)),                   value_expr(CompleteStr("null")));
-        assert_eq!(Ok((CompleteStr(""), Expr::Id("abc"))),  value_expr(CompleteStr("abc")));
+        assert_eq!(Ok((CompleteStr(""), ExprKind::Real(1.23f64).into())),          value_expr(CompleteStr("1.23")).strip_spans());
+        assert_eq!(Ok((CompleteStr(""), ExprKind::Int(123).into())),               value_expr(CompleteStr("123")).strip_spans());
+        assert_eq!(Ok((CompleteStr(""), ExprKind::Bool(true).into())),             value_expr(CompleteStr("true")).strip_spans());
+        assert_eq!(Ok((CompleteStr(""), ExprKind::Str("abc".into()).into())), value_expr(CompleteStr(r#""abc""#)).strip_spans());
+        assert_eq!(Ok((CompleteStr(""), ExprKind::None.into())),                   value_expr(CompleteStr("null")).strip_spans());
+        assert_eq!(Ok((CompleteStr(""), ExprKind::Id("abc").into())),  value_expr(CompleteStr("abc")).strip_spans());
 
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Expr::List(vec![
-                   Box::new(Expr::Int(1)),
-                   Box::new(Expr::Str("two")),
-                   Box::new(Expr::Bool(true)),
-                   Box::new(Expr::Real(4.56f64)),
-                ])
+                ExprKind::List(vec![
+                   Box::new(ExprKind::Int(1).into()),
+                   Box::new(ExprKind::Str("two".into()).into()),
+                   Box::new(ExprKind::Bool(true).into()),
+                   Box::new(ExprKind::Real(4.56f64).into()),
+                ]).into()
             )),
-            value_expr(CompleteStr(r#"[1, "two", true, 4.56]"#))
+            value_expr(CompleteStr(r#"[1, "two", true, 4.56]"#)).strip_spans()
         );
 
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Expr::Dict(vec![
-                   ("a",   Box::new(Expr::Int(1))),
-                   ("bcd", Box::new(Expr::Real(23.45f64)))
-                ])
+                ExprKind::Dict(vec![
+                   ("a".into(),   Box::new(ExprKind::Int(1).into())),
+                   ("bcd".into(), Box::new(ExprKind::Real(23.45f64).into()))
+                ]).into()
             )),
-            value_expr(CompleteStr(r#"{"a":1,"bcd":23.45}"#))
+            value_expr(CompleteStr(r#"{"a":1,"bcd":23.45}"#)).strip_spans()
         );
 
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Expr::FuncCall(
+                ExprKind::FuncCall(
                     "testFun",
                     vec![
-                        Box::new(Expr::Int(1)),
-                        Box::new(Expr::Int(2)),
-                        Box::new(Expr::Int(3)),
-                    ]
-                )
+                        Box::new(ExprKind::Int(1).into()),
+                        Box::new(ExprKind::Int(2).into()),
+                        Box::new(ExprKind::Int(3).into()),
+                    ],
+                    vec![]
+                ).into()
             )),
-            value_expr(CompleteStr("testFun(1, 2, 3)"))
-        );
-
-        assert_eq!(
-            Ok((CompleteStr(""), Expr::ListElement("a", Box::new(Expr::Int(1))))),
-            value_expr(CompleteStr("a[1]"))
+            value_expr(CompleteStr("testFun(1, 2, 3)")).strip_spans()
         );
 
         assert_eq!(
-            Ok((CompleteStr(""), Expr::UnaryOp(Opcode::Not, Box::new(Expr::Id("a"))))),
-            value_expr(CompleteStr("!a"))
+            Ok((CompleteStr(""), ExprKind::UnaryOp(Opcode::Not, Box::new(ExprKind::Id("a").into())).into())),
+            value_expr(CompleteStr("!a")).strip_spans()
         );
     }
 
     #[test]
     fn break_statement_valid() {
-        assert_eq!(Ok((CompleteStr(""), Stmt::Break)), break_statement(CompleteStr("break")));
-        assert_eq!(Ok((CompleteStr(""), Stmt::Break)), break_statement(CompleteStr(" break")));
-        assert_eq!(Ok((CompleteStr(""), Stmt::Break)), break_statement(CompleteStr("break ")));
-        assert_eq!(Ok((CompleteStr(""), Stmt::Break)), break_statement(CompleteStr(" break ")));
+        assert_eq!(Ok((CompleteStr(""), StmtKind::Break.into())), break_statement(CompleteStr("break")).strip_spans());
+        assert_eq!(Ok((CompleteStr(""), StmtKind::Break.into())), break_statement(CompleteStr(" break")).strip_spans());
+        assert_eq!(Ok((CompleteStr(""), StmtKind::Break.into())), break_statement(CompleteStr("break ")).strip_spans());
+        assert_eq!(Ok((CompleteStr(""), StmtKind::Break.into())), break_statement(CompleteStr(" break ")).strip_spans());
 
-        assert_eq!(Ok((CompleteStr(";"), Stmt::Break)), break_statement(CompleteStr("break;")));
+        assert_eq!(Ok((CompleteStr(";"), StmtKind::Break.into())), break_statement(CompleteStr("break;")).strip_spans());
+    }
+
+    #[test]
+    fn continue_statement_valid() {
+        assert_eq!(Ok((CompleteStr(""), StmtKind::Continue.into())), continue_statement(CompleteStr(" continue ")).strip_spans());
+        assert_eq!(Ok((CompleteStr(";"), StmtKind::Continue.into())), continue_statement(CompleteStr("continue;")).strip_spans());
+        continue_statement(CompleteStr("continued")).unwrap_err();
     }
 
     #[test]
     fn expr_statement_valid() {
         assert_eq!(
-            Ok((CompleteStr(""), Stmt::Expr(Expr::Id("a")))),
-            expr_statement(CompleteStr("a"))
+            Ok((CompleteStr(""), StmtKind::Expr(ExprKind::Id("a").into()).into())),
+            expr_statement(CompleteStr("a")).strip_spans()
         );
     }
 
@@ -881,40 +2395,131 @@ mod tests {
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Stmt::FnDef(
+                StmtKind::FnDef(
                     "abc",
                     vec![
-                        "a",
-                        "b",
-                        "c",
+                        "a".into(),
+                        "b".into(),
+                        "c".into(),
                     ],
+                    None,
                     vec![
-                        Stmt::Return(Expr::Id("a")),
+                        StmtKind::Return(ExprKind::Id("a").into()).into(),
                     ]
-                )
+                ).into()
+            )),
+            fndef_statement(CompleteStr("fn abc(a,b,c) { return a;}")).strip_spans()
+        );
+
+        // Default values and a rest parameter
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                StmtKind::FnDef(
+                    "f",
+                    vec![
+                        "a".into(),
+                        Param { id: "b", default: Some(ExprKind::Int(2).into()) },
+                        Param { id: "c", default: Some(ExprKind::List(vec![]).into()) },
+                    ],
+                    Some("rest"),
+                    vec![]
+                ).into()
+            )),
+            fndef_statement(CompleteStr("fn f(a, b = 2, c=[], ...rest) {}")).strip_spans()
+        );
+        assert_eq!(
+            Ok((CompleteStr(""), StmtKind::FnDef("f", vec![], Some("args"), vec![]).into())),
+            fndef_statement(CompleteStr("fn f( ... args ) {}")).strip_spans()
+        );
+
+        // The rest parameter must be last and named, and "=" must be followed by a default value
+        for (src, code) in [
+            ("fn f(...rest, a) {}", ERR_PARAMS_END),
+            ("fn f(a, ...) {}", ERR_IDENT),
+            ("fn f(a, b = ) {}", ERR_EXPR),
+        ].iter() {
+            match fndef_statement(CompleteStr(src)) {
+                Err(::nom::Err::Failure(Context::Code(_, ErrorKind::Custom(x)))) => assert_eq!(*code, x, "{}", src),
+                x => panic!("fndef_statement(): {} should fail, not {:?}", src, x),
+            }
+        }
+    }
+
+    #[test]
+    fn for_statement_valid() {
+        let body = || vec![StmtKind::Expr(ExprKind::FuncCall("print", vec![Box::new(ExprKind::Id("x").into())], vec![]).into()).into()];
+        assert_eq!(
+            Ok((CompleteStr(""), StmtKind::ForIn("x", ExprKind::Id("a").into(), body()).into())),
+            for_statement(CompleteStr("for x in a { print(x); }")).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                StmtKind::ForIn(
+                    "x",
+                    ExprKind::List(vec![Box::new(ExprKind::Int(1).into())]).into(),
+                    body()
+                ).into()
+            )),
+            for_statement(CompleteStr("for x in [1] { print(x); }")).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                StmtKind::ForRange("x", ExprKind::Int(0).into(), ExprKind::Id("n").into(), body()).into()
+            )),
+            for_statement(CompleteStr("for x in 0..n { print(x); }")).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                StmtKind::ForRange(
+                    "x",
+                    ExprKind::BinOp(Box::new(ExprKind::Id("a").into()), Opcode::Add, Box::new(ExprKind::Int(1).into())).into(),
+                    ExprKind::Real(2.5).into(),
+                    body()
+                ).into()
             )),
-            fndef_statement(CompleteStr("fn abc(a,b,c) { return a;}"))
+            for_statement(CompleteStr("for x in a + 1 .. 2.5 { print(x); }")).strip_spans()
         );
     }
 
+    #[test]
+    fn for_statement_invalid() {
+        let expected = |src| match parse(src) {
+            Err(e) => e.expected,
+            Ok(x) => panic!("parse(): {}: should fail, not {:?}", src, x),
+        };
+        assert_eq!("identifier", expected("for 1 in a { }"));
+        assert_eq!("'in'",       expected("for x a { }"));
+        assert_eq!("expression", expected("for x in ;"));
+        assert_eq!("expression", expected("for x in 0..;"));
+        assert_eq!("'{'",        expected("for x in a;"));
+    }
+
     #[test]
     fn if_statement_valid() {
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Stmt::If(
-                    Expr::Bool(true),
-                    vec![
-                        Stmt::Expr(
-                            Expr::FuncCall(
-                                "print",
-                                vec![Box::new(Expr::Int(1))],
-                            ),
-                        ),
-                    ]
-                )
+                StmtKind::If(
+                    vec![(
+                        ExprKind::Bool(true).into(),
+                        vec![
+                            StmtKind::Expr(
+                                ExprKind::FuncCall(
+                                    "print",
+                                    vec![Box::new(ExprKind::Int(1).into())],
+                                    vec![],
+                                ).into(),
+                            ).into(),
+                        ],
+                    )],
+                    None
+                ).into()
             )),
-            if_statement(CompleteStr(r#"if true { print(1); }"#))
+            if_statement(CompleteStr(r#"if true { print(1); }"#)).strip_spans()
         );
     }
 
@@ -923,35 +2528,196 @@ mod tests {
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Stmt::IfElse(
-                    Expr::Bool(true),
-                    vec![
-                        Stmt::Expr(
-                            Expr::FuncCall(
+                StmtKind::If(
+                    vec![(
+                        ExprKind::Bool(true).into(),
+                        vec![
+                            StmtKind::Expr(
+                                ExprKind::FuncCall(
+                                    "print",
+                                    vec![Box::new(ExprKind::Int(1).into())],
+                                    vec![],
+                                ).into(),
+                            ).into(),
+                        ],
+                    )],
+                    Some(vec![
+                        StmtKind::Expr(
+                            ExprKind::FuncCall(
                                 "print",
-                                vec![Box::new(Expr::Int(1))],
-                            ),
-                        ),
+                                vec![Box::new(ExprKind::Int(0).into())],
+                                vec![],
+                            ).into(),
+                        ).into(),
+                    ])
+                ).into()
+            )),
+            if_statement(CompleteStr(r#"if true { print(1); } else { print(0); }"#)).strip_spans()
+        );
+    }
+
+    #[test]
+    fn if_else_if_statement_valid() {
+        let print = |x| -> Stmt {
+            StmtKind::Expr(
+                ExprKind::FuncCall("print", vec![Box::new(ExprKind::Int(x).into())], vec![]).into()
+            ).into()
+        };
+
+        // Branches are flattened in order, with or without a final else
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                StmtKind::If(
+                    vec![
+                        (ExprKind::Id("a").into(), vec![print(1)]),
+                        (ExprKind::Id("b").into(), vec![print(2)]),
+                        (ExprKind::Id("c").into(), vec![print(3)]),
+                    ],
+                    Some(vec![print(0)])
+                ).into()
+            )),
+            if_statement(CompleteStr(
+                "if a { print(1); } else if b { print(2); } else if c { print(3); } else { print(0); }"
+            )).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                StmtKind::If(
+                    vec![
+                        (ExprKind::Id("a").into(), vec![print(1)]),
+                        (ExprKind::Id("b").into(), vec![print(2)]),
                     ],
+                    None
+                ).into()
+            )),
+            if_statement(CompleteStr("if a { print(1); } else if b { print(2); }")).strip_spans()
+        );
+    }
+
+    #[test]
+    fn if_statement_invalid() {
+        let expected = |src| match parse(src) {
+            Err(e) => e.expected,
+            Ok(x) => panic!("parse(): {}: should fail, not {:?}", src, x),
+        };
+        assert_eq!("expression", expected("if ;"));
+        assert_eq!("'{'",        expected("if a;"));
+        assert_eq!("'{'",        expected("if a { } else;"));
+        assert_eq!("expression", expected("if a { } else if ;"));
+        assert_eq!("'{'",        expected("if a { } else if b;"));
+    }
+
+    #[test]
+    fn if_expr_valid() {
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::If(
                     vec![
-                        Stmt::Expr(
-                            Expr::FuncCall(
-                                "print",
-                                vec![Box::new(Expr::Int(0))],
-                            ),
-                        ),
-                    ]
-                )
+                        (ExprKind::Id("a").into(), ExprKind::Int(1).into()),
+                        (ExprKind::Id("b").into(), ExprKind::Int(2).into()),
+                    ],
+                    Box::new(ExprKind::Int(3).into())
+                ).into()
+            )),
+            expr(CompleteStr("if a { 1 } else if b { 2 } else { 3 }")).strip_spans()
+        );
+
+        // If expressions can be used as operands
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                ExprKind::BinOp(
+                    Box::new(ExprKind::Int(1).into()),
+                    Opcode::Add,
+                    Box::new(
+                        ExprKind::If(
+                            vec![(ExprKind::Id("a").into(), ExprKind::Int(2).into())],
+                            Box::new(ExprKind::Int(3).into())
+                        ).into()
+                    )
+                ).into()
+            )),
+            expr(CompleteStr("1 + if a { 2 } else { 3 }")).strip_spans()
+        );
+    }
+
+    #[test]
+    fn if_expr_invalid() {
+        let expected = |src| match parse(src) {
+            Err(e) => e.expected,
+            Ok(x) => panic!("parse(): {}: should fail, not {:?}", src, x),
+        };
+        assert_eq!("expression", expected("let x = if ;"));
+        assert_eq!("'{'",        expected("let x = if a 1 else 2;"));
+        assert_eq!("expression", expected("let x = if a { } else { 2 };"));
+        assert_eq!("'}'",        expected("let x = if a { 1; } else { 2 };"));
+        assert_eq!("'else'",     expected("let x = if a { 1 };"));
+        assert_eq!("'else'",     expected("let x = if a { 1 } else if b { 2 };"));
+    }
+
+    #[test]
+    fn block_statement_valid() {
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                StmtKind::Block(vec![
+                    StmtKind::Let("a", ExprKind::Int(1).into()).into(),
+                    StmtKind::Block(vec![]).into(),
+                ]).into()
+            )),
+            block_statement(CompleteStr("{ let a = 1; { } }")).strip_spans()
+        );
+
+        // A "{" beginning a statement is never a dict literal
+        assert_eq!("';' or '}'", parse(r#"{"a": 1};"#).unwrap_err().expected);
+    }
+
+    #[test]
+    fn assignment_statement_valid() {
+        assert_eq!(
+            Ok((CompleteStr(""), StmtKind::Assign("a", None, ExprKind::Int(123).into()).into())),
+            assignment_statement(CompleteStr("a = 123")).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                StmtKind::Assign(
+                    "a",
+                    None,
+                    ExprKind::BinOp(
+                        Box::new(ExprKind::Id("a").into()),
+                        Opcode::Add,
+                        Box::new(ExprKind::Int(1).into())
+                    ).into()
+                ).into()
             )),
-            if_else_statement(CompleteStr(r#"if true { print(1); } else { print(0); }"#))
+            assignment_statement(CompleteStr("a = a + 1")).strip_spans()
         );
+
+        // Compound assignments
+        let compound = |op| {
+            Ok((CompleteStr(""), StmtKind::Assign("a", Some(op), ExprKind::Int(2).into()).into()))
+        };
+        assert_eq!(compound(Opcode::Add), assignment_statement(CompleteStr("a += 2")).strip_spans());
+        assert_eq!(compound(Opcode::Sub), assignment_statement(CompleteStr("a -= 2")).strip_spans());
+        assert_eq!(compound(Opcode::Mul), assignment_statement(CompleteStr("a *= 2")).strip_spans());
+        assert_eq!(compound(Opcode::Div), assignment_statement(CompleteStr("a/=2")).strip_spans());
+        assert_eq!(compound(Opcode::Mod), assignment_statement(CompleteStr("a %= 2")).strip_spans());
+
+        // "==" is a comparison, not an assignment
+        assert!(assignment_statement(CompleteStr("a == 1")).is_err());
+        assert_eq!("expression", parse("a = ;").unwrap_err().expected);
+        assert_eq!("expression", parse("a += ;").unwrap_err().expected);
     }
 
     #[test]
     fn let_statement_valid() {
         assert_eq!(
-            Ok((CompleteStr(""), Stmt::Let("a", Expr::Int(123)))),
-            let_statement(CompleteStr("let a = 123"))
+            Ok((CompleteStr(""), StmtKind::Let("a", ExprKind::Int(123).into()).into())),
+            let_statement(CompleteStr("let a = 123")).strip_spans()
         );
     }
 
@@ -960,24 +2726,54 @@ mod tests {
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Stmt::ListItemAssignment(
+                StmtKind::ListItemAssignment(
                     "a",
-                    Expr::Int(1),
-                    Expr::Int(2)
-                )
+                    vec![ExprKind::Int(1).into()],
+                    None,
+                    ExprKind::Int(2).into()
+                ).into()
             )),
-            list_assignment_statement(CompleteStr("a[1] = 2"))
+            list_assignment_statement(CompleteStr("a[1] = 2")).strip_spans()
         );
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Stmt::ListItemAssignment(
+                StmtKind::ListItemAssignment(
                     "a",
-                    Expr::Str("idx"),
-                    Expr::Int(2)
-                )
+                    vec![ExprKind::Str("idx".into()).into()],
+                    None,
+                    ExprKind::Int(2).into()
+                ).into()
+            )),
+            list_assignment_statement(CompleteStr(r#"a["idx"] = 2"#)).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                StmtKind::ListItemAssignment(
+                    "a",
+                    vec![ExprKind::Id("i").into()],
+                    Some(Opcode::Mul),
+                    ExprKind::Int(2).into()
+                ).into()
+            )),
+            list_assignment_statement(CompleteStr("a[i] *= 2")).strip_spans()
+        );
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                StmtKind::ListItemAssignment(
+                    "cfg",
+                    vec![
+                        ExprKind::Str("net".into()).into(),
+                        ExprKind::Int(0).into(),
+                        ExprKind::Str("port".into()).into(),
+                    ],
+                    None,
+                    ExprKind::Int(80).into()
+                ).into()
             )),
-            list_assignment_statement(CompleteStr(r#"a["idx"] = 2"#))
+            list_assignment_statement(CompleteStr("cfg.net[0][\"port\"] = 80")).strip_spans()
         );
     }
 
@@ -986,92 +2782,357 @@ mod tests {
         assert_eq!(
             Ok((
                 CompleteStr(""),
-                Stmt::Loop(
+                StmtKind::Loop(
                     vec![
-                        Stmt::Expr(
-                            Expr::FuncCall(
+                        StmtKind::Expr(
+                            ExprKind::FuncCall(
                                 "print",
-                                vec![Box::new(Expr::Int(1))]
-                            )
-                        ),
+                                vec![Box::new(ExprKind::Int(1).into())],
+                                vec![]
+                            ).into()
+                        ).into(),
                     ]
-                )
+                ).into()
             )),
-            loop_statement(CompleteStr("loop { print(1); }"))
+            loop_statement(CompleteStr("loop { print(1); }")).strip_spans()
         );
     }
 
     #[test]
     fn return_statement_valid() {
         assert_eq!(
-            Ok((CompleteStr(""), Stmt::Return(Expr::Int(123)))),
-            return_statement(CompleteStr("return 123"))
+            Ok((CompleteStr(""), StmtKind::Return(ExprKind::Int(123).into()).into())),
+            return_statement(CompleteStr("return 123")).strip_spans()
+        );
+    }
+
+    #[test]
+    fn while_statement_valid() {
+        assert_eq!(
+            Ok((
+                CompleteStr(""),
+                StmtKind::While(
+                    ExprKind::BinOp(Box::new(ExprKind::Id("a").into()), Opcode::LessThan, Box::new(ExprKind::Int(1).into())).into(),
+                    vec![StmtKind::Continue.into()]
+                ).into()
+            )),
+            while_statement(CompleteStr("while a < 1 { continue; }")).strip_spans()
+        );
+    }
+
+    #[test]
+    fn comments_valid() {
+        assert_eq!(Ok((CompleteStr("\na"), CompleteStr("// c"))), line_comment(CompleteStr("// c\na")));
+        assert_eq!(Ok((CompleteStr(""), CompleteStr("//"))), line_comment(CompleteStr("//")));
+        assert_eq!(Ok((CompleteStr(" a"), CompleteStr("/* c */"))), block_comment(CompleteStr("/* c */ a")));
+        assert_eq!(
+            Ok((CompleteStr(""), CompleteStr("/* a /* b */ c */"))),
+            block_comment(CompleteStr("/* a /* b */ c */"))
+        );
+        assert_eq!(
+            Ok((CompleteStr("a"), CompleteStr(" // c\n /* d\n */\t"))),
+            skip(CompleteStr(" // c\n /* d\n */\ta"))
+        );
+        assert_eq!(Ok((CompleteStr("a"), CompleteStr(""))), skip(CompleteStr("a")));
+        assert_eq!(Ok((CompleteStr("/ a"), CompleteStr(""))), skip(CompleteStr("/ a")));
+    }
+
+    #[test]
+    fn comments_invalid() {
+        match block_comment(CompleteStr("/* a /* b */")) {
+            Err(::nom::Err::Failure(Context::Code(_, ErrorKind::Custom(ERR_COMMENT_END)))) => {},
+            x => panic!("block_comment(): unterminated comment should fail, not {:?}", x),
+        };
+        assert!(block_comment(CompleteStr("a /* b */")).is_err());
+    }
+
+    #[test]
+    fn parse_valid() {
+        assert_eq!(Ok(vec![]), parse("").strip_spans());
+        assert_eq!(Ok(vec![]), parse("  \n ").strip_spans());
+        assert_eq!(
+            Ok(vec![StmtKind::Expr(ExprKind::Id("a").into()).into(), StmtKind::Expr(ExprKind::Id("b").into()).into()]),
+            parse(" a; b; ").strip_spans()
+        );
+        assert_eq!(
+            Ok(vec![StmtKind::Loop(vec![StmtKind::Break.into(), StmtKind::Continue.into()]).into()]),
+            parse("loop { break; continue; }").strip_spans()
+        );
+        assert_eq!(Ok(vec![StmtKind::Expr(ExprKind::Id("letter").into()).into()]), parse("letter").strip_spans());
+
+        // Comments are allowed anywhere whitespace is
+        assert_eq!(Ok(vec![]), parse("// comment").strip_spans());
+        for src in ["// header\nx = 5;", "  x = 5;", "/* c */ x = 5"].iter() {
+            assert_eq!(
+                Ok(vec![StmtKind::Assign("x", None, ExprKind::Int(5).into()).into()]),
+                parse(src).strip_spans(),
+                "{}", src
+            );
+        }
+        assert_eq!(
+            Ok(vec![StmtKind::ListItemAssignment(
+                "x",
+                vec![ExprKind::Int(0).into()],
+                None,
+                ExprKind::Int(5).into()
+            ).into()]),
+            parse("/* c */ x[0] = 5;").strip_spans()
+        );
+        assert_eq!(Ok(vec![]), parse("/* a /* nested */ comment */").strip_spans());
+        assert_eq!(
+            Ok(vec![
+                StmtKind::Let(
+                    "a",
+                    ExprKind::List(vec![
+                        Box::new(ExprKind::Int(1).into()),
+                        Box::new(ExprKind::BinOp(
+                            Box::new(ExprKind::Int(2).into()),
+                            Opcode::Div,
+                            Box::new(ExprKind::Int(3).into())
+                        ).into()),
+                    ]).into()
+                ).into(),
+                StmtKind::Loop(vec![
+                    StmtKind::Expr(ExprKind::FuncCall(
+                        "f",
+                        vec![Box::new(ExprKind::Dict(vec![("b".into(), Box::new(ExprKind::Int(4).into()))]).into())],
+                        vec![]
+                    ).into()).into(),
+                    StmtKind::Break.into(),
+                ]).into(),
+            ]),
+            parse(
+                "// Start\n\
+                 let /* name */ a = [ // first\n 1, /* second */ 2 /* div */ / 3 ];\n\
+                 loop { // body\n\
+                     f( /* arg */ { /* key */ \"b\": /* val */ 4 } // end\n );\n\
+                     break; // done\n\
+                 }; /* end */"
+            ).strip_spans()
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        // Missing operand
+        let e = parse("let a = 1 +;").unwrap_err();
+        assert_eq!((11, 1, 12), (e.offset, e.line, e.column));
+        assert_eq!(";", e.snippet);
+        assert_eq!("expression", e.expected);
+
+        // Missing statement separator on a later line
+        let e = parse("let a = 1;\nlet b = 2\n  let c = 3;").unwrap_err();
+        assert_eq!((23, 3, 3), (e.offset, e.line, e.column));
+        assert_eq!("let c = 3;", e.snippet);
+        assert_eq!("';' or end of input", e.expected);
+
+        // Unparseable statement
+        let e = parse("let a = 1; @").unwrap_err();
+        assert_eq!((11, 1, 12), (e.offset, e.line, e.column));
+        assert_eq!("statement", e.expected);
+
+        // Errors within nested constructs
+        let e = parse("fn f(a, b {\n}").unwrap_err();
+        assert_eq!((1, 11), (e.line, e.column));
+        assert_eq!("',' or ')'", e.expected);
+
+        let e = parse("loop {\n  print(1, [2, 3);\n}").unwrap_err();
+        assert_eq!((2, 17), (e.line, e.column));
+        assert_eq!("',' or ']'", e.expected);
+
+        let e = parse("if a { let b = 1 let c = 2; }").unwrap_err();
+        assert_eq!((1, 18), (e.line, e.column));
+        assert_eq!("';' or '}'", e.expected);
+
+        let e = parse("let a = 1; /* a /* b */").unwrap_err();
+        assert_eq!("'*/'", e.expected);
+        assert_eq!(e.offset, 23);
+
+        let e = parse("let a = // 1;\n;").unwrap_err();
+        assert_eq!((2, 1), (e.line, e.column));
+        assert_eq!("expression", e.expected);
+
+        let e = parse("let = 1;").unwrap_err();
+        assert_eq!("identifier", e.expected);
+
+        let e = parse("let a = \"abc;").unwrap_err();
+        assert_eq!("'\"'", e.expected);
+        assert_eq!("", e.snippet);
+        assert_eq!(
+            "line 1, column 14: expected '\"', found end of input",
+            format!("{}", e)
+        );
+    }
+
+    #[test]
+    fn parse_spans() {
+        let span = |start, end, line, column| Span { start, end, line, column };
+        let stmts = parse("let a = 1 +\n  b;\nif a {\n  f((a), 2);\n}").unwrap();
+
+        // Statements exclude surrounding whitespace and separators
+        assert_eq!(span(0, 15, 1, 1), stmts[0].span);
+        assert_eq!(span(17, 38, 3, 1), stmts[1].span);
+        match stmts[0].kind {
+            StmtKind::Let(_, ref x) => {
+                assert_eq!(span(8, 15, 1, 9), x.span);
+                match x.kind {
+                    ExprKind::BinOp(ref l, _, ref r) => {
+                        assert_eq!(span(8, 9, 1, 9), l.span);
+                        assert_eq!(span(14, 15, 2, 3), r.span);
+                    },
+                    _ => panic!("parse(): Let value is not ExprKind::BinOp"),
+                }
+            },
+            _ => panic!("parse(): first statement is not StmtKind::Let"),
+        }
+
+        // Nested statements and parenthesised expressions
+        match stmts[1].kind {
+            StmtKind::If(ref branches, _) => {
+                let (ref cond, ref stmts) = branches[0];
+                assert_eq!(span(20, 21, 3, 4), cond.span);
+                assert_eq!(span(26, 35, 4, 3), stmts[0].span);
+                match stmts[0].kind {
+                    StmtKind::Expr(Expr { kind: ExprKind::FuncCall(_, ref args, _), .. }) => {
+                        assert_eq!(span(28, 31, 4, 5), args[0].span);
+                        assert_eq!(span(33, 34, 4, 10), args[1].span);
+                    },
+                    _ => panic!("parse(): If body is not a function call"),
+                }
+            },
+            _ => panic!("parse(): second statement is not StmtKind::If"),
+        }
+
+        // Spans exclude surrounding comments, but not comments within the element
+        let stmts = parse("/* a */ let b = [1, /* c */ \"//\"] // d\n;").unwrap();
+        assert_eq!(span(8, 33, 1, 9), stmts[0].span);
+
+        // Trailing comments are excluded from nested elements as well
+        let stmts = parse("return f(1 /* a */, [2 // b\n]) /* c */;").unwrap();
+        assert_eq!(span(0, 30, 1, 1), stmts[0].span);
+        match stmts[0].kind {
+            StmtKind::Return(Expr { kind: ExprKind::FuncCall(_, ref args, _), span: x }) => {
+                assert_eq!(span(7, 30, 1, 8), x);
+                assert_eq!(span(9, 10, 1, 10), args[0].span);
+                assert_eq!(span(20, 29, 1, 21), args[1].span);
+            },
+            _ => panic!("parse(): statement is not a returned function call"),
+        }
+
+        // Spans compare structurally, an unknown Span only equals itself
+        assert!(Span::default() != span(1, 2, 3, 4));
+        assert!(span(1, 2, 3, 4) != span(1, 2, 3, 5));
+        assert_eq!(
+            Ok(vec![StmtKind::Expr(ExprKind::Id("a").into()).into()]),
+            parse("a").strip_spans()
         );
     }
 
     #[test]
     fn statement_valid() {
+        match statement(CompleteStr("a = 1")) {
+            Err(_) => panic!("statement(): Assign: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::Assign(_, _, _) => {},
+                _ => panic!("statement(): Assign: not Stmt::Assign"),
+            },
+        }
         match statement(CompleteStr("break")) {
-            Err(_) => assert!(false, "statement(): Break: returned error"),
-            Ok(s) => match s.1 {
-                Stmt::Break => {},
-                _ => assert!(false, "statement(): Break: not Stmt::Break"),
+            Err(_) => panic!("statement(): Break: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::Break => {},
+                _ => panic!("statement(): Break: not Stmt::Break"),
             },
         }
         match statement(CompleteStr("fn a(b) { return a; }")) {
-            Err(_) => assert!(false, "statement(): FnDef: returned error"),
-            Ok(s) => match s.1 {
-                Stmt::FnDef(_, _, _) => {},
-                _ => assert!(false, "statement(): FnDef: not Stmt::FnDef"),
+            Err(_) => panic!("statement(): FnDef: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::FnDef(_, _, _, _) => {},
+                _ => panic!("statement(): FnDef: not Stmt::FnDef"),
             },
         }
         match statement(CompleteStr("if true { print(1); }")) {
-            Err(_) => assert!(false, "statement(): If: returned error"),
-            Ok(s) => match s.1 {
-                Stmt::If(_, _) => {},
-                _ => assert!(false, "statement(): If: not Stmt::If"),
+            Err(_) => panic!("statement(): If: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::If(_, _) => {},
+                _ => panic!("statement(): If: not Stmt::If"),
             },
         }
         match statement(CompleteStr("if true { print(1); } else { print(0); }")) {
-            Err(_) => assert!(false, "statement(): IfElse: returned error"),
-            Ok(s) => match s.1 {
-                Stmt::IfElse(_, _, _) => {},
-                _ => assert!(false, "statement(): IfElse: not Stmt::IfElse"),
+            Err(_) => panic!("statement(): If with else: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::If(_, Some(_)) => {},
+                _ => panic!("statement(): If with else: not Stmt::If"),
             },
         }
         match statement(CompleteStr("let a = 1")) {
-            Err(_) => assert!(false, "statement(): Let: returned error"),
-            Ok(s) => match s.1 {
-                Stmt::Let(_, _) => {},
-                _ => assert!(false, "statement(): Let: not Stmt::Let"),
+            Err(_) => panic!("statement(): Let: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::Let(_, _) => {},
+                _ => panic!("statement(): Let: not Stmt::Let"),
             },
         }
         match statement(CompleteStr("a[1] = 2")) {
-            Err(_) => assert!(false, "statement(): ListItemAssignment: returned error"),
-            Ok(s) => match s.1 {
-                Stmt::ListItemAssignment(_, _, _) => {},
-                _ => assert!(false, "statement(): ListItemAssignment: not Stmt::ListItemAssignment"),
+            Err(_) => panic!("statement(): ListItemAssignment: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::ListItemAssignment(_, _, _, _) => {},
+                _ => panic!("statement(): ListItemAssignment: not Stmt::ListItemAssignment"),
             },
         }
         match statement(CompleteStr("loop { print(1); }")) {
-            Err(_) => assert!(false, "statement(): Loop: returned error"),
-            Ok(s) => match s.1 {
-                Stmt::Loop(_) => {},
-                _ => assert!(false, "statement(): Loop: not Stmt::Loop"),
+            Err(_) => panic!("statement(): Loop: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::Loop(_) => {},
+                _ => panic!("statement(): Loop: not Stmt::Loop"),
             },
         }
         match statement(CompleteStr("return 1")) {
-            Err(_) => assert!(false, "statement(): Return: returned error"),
-            Ok(s) => match s.1 {
-                Stmt::Return(_) => {},
-                _ => assert!(false, "statement(): Return: not Stmt::Return"),
+            Err(_) => panic!("statement(): Return: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::Return(_) => {},
+                _ => panic!("statement(): Return: not Stmt::Return"),
+            },
+        }
+        match statement(CompleteStr("continue")) {
+            Err(_) => panic!("statement(): Continue: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::Continue => {},
+                _ => panic!("statement(): Continue: not Stmt::Continue"),
+            },
+        }
+        match statement(CompleteStr("for a in b { print(a); }")) {
+            Err(_) => panic!("statement(): ForIn: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::ForIn(_, _, _) => {},
+                _ => panic!("statement(): ForIn: not Stmt::ForIn"),
+            },
+        }
+        match statement(CompleteStr("for a in 1..b { print(a); }")) {
+            Err(_) => panic!("statement(): ForRange: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::ForRange(_, _, _, _) => {},
+                _ => panic!("statement(): ForRange: not Stmt::ForRange"),
+            },
+        }
+        match statement(CompleteStr("while true { print(1); }")) {
+            Err(_) => panic!("statement(): While: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::While(_, _) => {},
+                _ => panic!("statement(): While: not Stmt::While"),
+            },
+        }
+        match statement(CompleteStr("format(1)")) {
+            Err(_) => panic!("statement(): Expr: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::Expr(_) => {},
+                _ => panic!("statement(): Expr: not Stmt::Expr"),
             },
         }
         match statement(CompleteStr("print(1)")) {
-            Err(_) => assert!(false, "statement(): Expr: returned error"),
-            Ok(s) => match s.1 {
-                Stmt::Expr(_) => {},
-                _ => assert!(false, "statement(): Expr: not Stmt::Expr"),
+            Err(_) => panic!("statement(): Expr: returned error"),
+            Ok(s) => match s.1.kind {
+                StmtKind::Expr(_) => {},
+                _ => panic!("statement(): Expr: not Stmt::Expr"),
             },
         }
     }